@@ -0,0 +1,199 @@
+use alloc::vec::Vec;
+use core::{ptr, slice};
+use memory::{self, NO_EXECUTE};
+
+// ACPI.
+// Solo quanto serve per trovare la MADT: RSDP nella memoria del BIOS,
+// RSDT e le sue tabelle. Le tabelle vengono mappate in sola lettura
+// nella finestra fisica del MemoryController solo mentre servono, e
+// tolte in ordine inverso così la finestra torna libera.
+
+const RSDP_SIGNATURE: &'static [u8; 8] = b"RSD PTR ";
+const MADT_SIGNATURE: &'static [u8; 4] = b"APIC";
+const SDT_HEADER_SIZE: usize = 36;
+
+// Dove il BIOS può lasciare la RSDP.
+const EBDA_POINTER: usize = 0x40e;
+const BIOS_AREA_START: usize = 0xe0000;
+const BIOS_AREA_END: usize = 0x100000;
+
+// MADT.
+
+pub struct IoApicInfo {
+	pub id: u8,
+	pub address: usize,
+	pub gsi_base: u32,
+}
+
+// Le IRQ ISA sono identiche alle GSI, salvo queste eccezioni.
+pub struct InterruptOverride {
+	pub irq: u8,
+	pub gsi: u32,
+	pub flags: u16,
+}
+
+pub struct Madt {
+	pub local_apic_address: usize,
+	// Flag PCAT_COMPAT: ci sono anche i PIC 8259.
+	pub has_legacy_pics: bool,
+	pub io_apics: Vec<IoApicInfo>,
+	pub overrides: Vec<InterruptOverride>,
+}
+
+const MADT_IO_APIC: u8 = 1;
+const MADT_INTERRUPT_OVERRIDE: u8 = 2;
+const MADT_LOCAL_APIC_OVERRIDE: u8 = 5;
+
+pub fn find_madt() -> Option<Madt> {
+	let rsdt = match find_rsdt() {
+		Some(rsdt) => map_table(rsdt),
+		None => return None,
+	};
+
+	let mut madt = None;
+	let entries = (table_length(rsdt) - SDT_HEADER_SIZE) / 4;
+	for index in 0..entries {
+		let table = unsafe { read::<u32>(rsdt + SDT_HEADER_SIZE + index * 4) } as usize;
+		let table = map_table(table);
+		if signature(table) == MADT_SIGNATURE {
+			madt = Some(parse_madt(table));
+		}
+		unmap_table(table);
+
+		if madt.is_some() {
+			break;
+		}
+	}
+
+	unmap_table(rsdt);
+	madt
+}
+
+fn parse_madt(madt: usize) -> Madt {
+	let mut result = Madt {
+		local_apic_address: unsafe { read::<u32>(madt + SDT_HEADER_SIZE) } as usize,
+		has_legacy_pics: unsafe { read::<u32>(madt + SDT_HEADER_SIZE + 4) } & 1 != 0,
+		io_apics: Vec::new(),
+		overrides: Vec::new(),
+	};
+
+	let end = madt + table_length(madt);
+	let mut entry = madt + SDT_HEADER_SIZE + 8;
+	while entry + 2 <= end {
+		let (entry_type, length) = unsafe { (read::<u8>(entry), read::<u8>(entry + 1)) };
+		if length < 2 {
+			break;
+		}
+
+		unsafe {
+			match entry_type {
+				MADT_IO_APIC => result.io_apics.push(IoApicInfo {
+					id: read::<u8>(entry + 2),
+					address: read::<u32>(entry + 4) as usize,
+					gsi_base: read::<u32>(entry + 8),
+				}),
+				MADT_INTERRUPT_OVERRIDE => result.overrides.push(InterruptOverride {
+					irq: read::<u8>(entry + 3),
+					gsi: read::<u32>(entry + 4),
+					flags: read::<u16>(entry + 8),
+				}),
+				MADT_LOCAL_APIC_OVERRIDE => {
+					result.local_apic_address = read::<u64>(entry + 4) as usize;
+				}
+				_ => {}
+			}
+		}
+
+		entry += length as usize;
+	}
+
+	result
+}
+
+// ---
+
+// RSDP.
+
+// Cerca la RSDP nel primo KiB della EBDA e nell'area del BIOS,
+// allineata a 16 byte. Ritorna l'indirizzo fisico della RSDT.
+fn find_rsdt() -> Option<usize> {
+	let pointer = map_physical(EBDA_POINTER, 2);
+	let ebda = unsafe { read::<u16>(pointer) } as usize * 16;
+	unmap_physical(pointer, 2);
+
+	if ebda != 0 {
+		if let Some(rsdt) = search_rsdp(ebda, ebda + 1024) {
+			return Some(rsdt);
+		}
+	}
+
+	search_rsdp(BIOS_AREA_START, BIOS_AREA_END)
+}
+
+// L'area resta mappata solo per la ricerca: della RSDP basta
+// l'indirizzo della RSDT.
+fn search_rsdp(start: usize, end: usize) -> Option<usize> {
+	let area = map_physical(start, end - start);
+	let rsdt = (0..(end - start) / 16)
+		.map(|index| area + index * 16)
+		.find(|&candidate| {
+			let bytes = unsafe { slice::from_raw_parts(candidate as *const u8, 20) };
+			&bytes[0..8] == RSDP_SIGNATURE && checksum(bytes)
+		})
+		.map(|rsdp| unsafe { read::<u32>(rsdp + 16) } as usize);
+	unmap_physical(area, end - start);
+
+	rsdt
+}
+
+// ---
+
+// SDT.
+
+// Mappa prima l'header e poi, nota la lunghezza, tutta la tabella.
+fn map_table(address: usize) -> usize {
+	let header = map_physical(address, SDT_HEADER_SIZE);
+	let length = table_length(header);
+	unmap_physical(header, SDT_HEADER_SIZE);
+	let table = map_physical(address, length);
+
+	let bytes = unsafe { slice::from_raw_parts(table as *const u8, length) };
+	assert! (checksum(bytes), "Invalid ACPI table checksum at {:#x}.", address);
+	table
+}
+
+fn unmap_table(table: usize) {
+	unmap_physical(table, table_length(table));
+}
+
+fn signature(table: usize) -> &'static [u8] {
+	unsafe { slice::from_raw_parts(table as *const u8, 4) }
+}
+
+fn table_length(table: usize) -> usize {
+	unsafe { read::<u32>(table + 4) as usize }
+}
+
+// ---
+
+// La somma di tutti i byte deve essere zero.
+fn checksum(bytes: &[u8]) -> bool {
+	bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) == 0
+}
+
+fn map_physical(address: usize, size: usize) -> usize {
+	memory::with_memory_controller(|controller| {
+		controller.map_physical(address, size, NO_EXECUTE)
+	})
+}
+
+fn unmap_physical(address: usize, size: usize) {
+	memory::with_memory_controller(|controller| {
+		controller.unmap_physical(address, size)
+	})
+}
+
+// Le tabelle ACPI non garantiscono l'allineamento dei campi.
+unsafe fn read<T>(address: usize) -> T {
+	ptr::read_unaligned(address as *const T)
+}
@@ -27,7 +27,8 @@ start:
 	mov [p4_table + 511 * 8], eax 	; la 511ma pag di P$ punta a P4
 	call set_up_SSE
 	
-	; Carica la GDT a 64 bit.
+	; Carica la GDT a 64 bit. Serve solo per entrare in long mode:
+	; interrupts::init la sostituisce con una GDT con TSS costruita in Rust.
 	lgdt [gdt64.pointer]
 	
 	; Aggiorna i selettori.
@@ -201,7 +202,8 @@ p3_table:		; Page-Directory Pointer Table (PDP).
 p2_table:		; Page-Directory Table (PD).
 	resb 4096 
 stack_bottom:
-	resb 4096	; reserve byte, in particolare 4096 bit.
+	resb 4096 * 4	; reserve byte, 16 KiB di stack. La pagina di p4_table
+					; diventa la guard page dopo il remapping del kernel.
 stack_top:
 ; END STACK ---------------------------------------------
 
@@ -0,0 +1,67 @@
+global isr_stub_table
+extern interrupt_dispatch
+
+section .text
+bits 64
+
+; -
+; STUB DEGLI INTERRUPT
+; -
+
+; Ogni vettore ha un piccolo stub che uniforma lo stack (error code fittizio
+; se la CPU non lo fornisce, poi il numero del vettore) e salta a isr_common.
+; Solo alcune eccezioni della CPU hanno un error code.
+
+%assign i 0
+%rep 256
+isr_stub_%+i:
+	%if !(i == 8 || i == 10 || i == 11 || i == 12 || i == 13 || i == 14 || i == 17 || i == 21 || i == 29 || i == 30)
+	push 0			; Error code fittizio.
+	%endif
+	push i			; Vettore.
+	jmp isr_common
+%assign i i+1
+%endrep
+
+; Salva i registri scratch e chiama interrupt_dispatch con un puntatore
+; al contesto (InterruptContext in interrupts/mod.rs). Lo stato SSE/x87
+; non serve: il target (x86_64-degrad_os.json) compila il kernel senza SSE.
+isr_common:
+	push rax
+	push rcx
+	push rdx
+	push rsi
+	push rdi
+	push r8
+	push r9
+	push r10
+	push r11
+	mov rdi, rsp		; Primo argomento: il contesto.
+
+	; La CPU allinea lo stack a 16 byte prima del frame di interrupt:
+	; dopo 5 + 2 + 9 push rsp è di nuovo allineato, come richiede la call.
+	cld
+	call interrupt_dispatch
+
+	pop r11
+	pop r10
+	pop r9
+	pop r8
+	pop rdi
+	pop rsi
+	pop rdx
+	pop rcx
+	pop rax
+	add rsp, 16			; Vettore ed error code.
+	iretq
+; -------------------------------------------------------
+
+; TABELLA DEGLI STUB ------------------------------------
+section .rodata
+isr_stub_table:
+%assign i 0
+%rep 256
+	dq isr_stub_%+i
+%assign i i+1
+%endrep
+; END TABELLA -------------------------------------------
@@ -0,0 +1,84 @@
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use cpu;
+use timer;
+
+// CLOCK.
+// Nanosecondi dall'avvio letti dal TSC, calibrato contro il PIT.
+// Senza calibrazione si ripiega sui tick del timer.
+
+const CALIBRATION_MS: usize = 50;
+const CALIBRATION_ROUNDS: usize = 3;
+
+// Valore del TSC all'avvio e frequenza in kHz, 0 se non calibrato.
+static BOOT_TSC: AtomicUsize = AtomicUsize::new(0);
+static TSC_KHZ: AtomicUsize = AtomicUsize::new(0);
+
+// Da chiamare presto, con gli interrupt disabilitati: ogni interrupt
+// durante la misura la allunga. Si tiene la misura più breve.
+pub fn init() {
+	BOOT_TSC.store(cpu::rdtsc() as usize, Ordering::SeqCst);
+
+	let mut cycles = u64::max_value();
+	for _ in 0..CALIBRATION_ROUNDS {
+		let start = cpu::rdtsc();
+		timer::busy_wait_ms(CALIBRATION_MS);
+		let elapsed = cpu::rdtsc() - start;
+		if elapsed < cycles {
+			cycles = elapsed;
+		}
+	}
+
+	// Il PIT conta un numero intero di cicli a 1.193182 MHz.
+	let pit_count = (timer::PIT_FREQUENCY * CALIBRATION_MS / 1000) as u64;
+	let khz = cycles * timer::PIT_FREQUENCY as u64 / pit_count / 1000;
+	TSC_KHZ.store(khz as usize, Ordering::SeqCst);
+
+	println! ("TSC: {}.{:03} MHz{}", khz / 1000, khz % 1000,
+			  if is_invariant() { ", invariant" } else { ", not invariant" });
+}
+
+// Con un TSC non invariante la frequenza può cambiare con i P-state:
+// le misure restano utili per confronti, non come tempo assoluto.
+pub fn is_invariant() -> bool {
+	cpu::has_invariant_tsc()
+}
+
+pub fn tsc_khz() -> usize {
+	TSC_KHZ.load(Ordering::Relaxed)
+}
+
+// Nanosecondi dall'avvio.
+pub fn now() -> u64 {
+	let khz = tsc_khz() as u64;
+	if khz == 0 {
+		return timer::uptime() as u64 * 1_000_000;
+	}
+
+	// Diviso in due per non andare in overflow dopo poche ore.
+	let cycles = cpu::rdtsc() - BOOT_TSC.load(Ordering::Relaxed) as u64;
+	cycles / khz * 1_000_000 + cycles % khz * 1_000_000 / khz
+}
+
+// ---
+
+// TIMESTAMP.
+
+// Istante in nanosecondi, stampato come [secondi.microsecondi].
+#[derive(Clone, Copy)]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+	pub fn now() -> Timestamp {
+		Timestamp(now())
+	}
+}
+
+impl fmt::Display for Timestamp {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let micros = self.0 / 1000;
+		write!(f, "[{:5}.{:06}]", micros / 1_000_000, micros % 1_000_000)
+	}
+}
+
+// ---
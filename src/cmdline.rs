@@ -0,0 +1,54 @@
+use core::{slice, str};
+
+// COMMAND LINE.
+// La riga di comando che GRUB passa al kernel (in grub.cfg, dopo il
+// percorso del kernel), come opzioni `chiave=valore` separate da spazi.
+// multiboot2 non espone il suo tag: lo si cerca direttamente.
+
+const TAG_END: u32 = 0;
+const TAG_COMMAND_LINE: u32 = 1;
+
+// Va chiamata finché le informazioni multiboot sono mappate.
+pub fn command_line(multiboot_info_pointer: usize) -> &'static str {
+	unsafe {
+		let total_size = *(multiboot_info_pointer as *const u32) as usize;
+		let end = multiboot_info_pointer + total_size;
+
+		// I tag partono dopo total_size e reserved, allineati a 8 byte.
+		let mut tag = multiboot_info_pointer + 8;
+		while tag + 8 <= end {
+			let tag_type = *(tag as *const u32);
+			let tag_size = *((tag + 4) as *const u32) as usize;
+
+			match tag_type {
+				TAG_END => break,
+				TAG_COMMAND_LINE => return c_string(tag + 8, tag + tag_size),
+				_ => tag += (tag_size + 7) & !7,
+			}
+		}
+	}
+
+	""
+}
+
+// Valore dell'opzione `key=value`, se presente.
+pub fn option(command_line: &'static str, key: &str) -> Option<&'static str> {
+	command_line.split_whitespace()
+		.filter_map(|option| {
+			let mut parts = option.splitn(2, '=');
+			match (parts.next(), parts.next()) {
+				(Some(name), Some(value)) if name == key => Some(value),
+				_ => None,
+			}
+		})
+		.last()
+}
+
+// Stringa terminata da zero fra start ed end, vuota se non è UTF-8.
+unsafe fn c_string(start: usize, end: usize) -> &'static str {
+	let bytes = slice::from_raw_parts(start as *const u8, end - start);
+	let length = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+	str::from_utf8(&bytes[..length]).unwrap_or("")
+}
+
+// ---
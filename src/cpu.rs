@@ -0,0 +1,126 @@
+// CPUID.
+
+pub struct CpuidResult {
+	pub eax: u32,
+	pub ebx: u32,
+	pub ecx: u32,
+	pub edx: u32,
+}
+
+pub fn cpuid(leaf: u32) -> CpuidResult {
+	cpuid_count(leaf, 0)
+}
+
+pub fn cpuid_count(leaf: u32, subleaf: u32) -> CpuidResult {
+	let (eax, ebx, ecx, edx): (u32, u32, u32, u32);
+	unsafe {
+		asm!("cpuid"
+			 : "={eax}"(eax), "={ebx}"(ebx), "={ecx}"(ecx), "={edx}"(edx)
+			 : "{eax}"(leaf), "{ecx}"(subleaf)
+			 :
+			 : "volatile");
+	}
+	CpuidResult { eax: eax, ebx: ebx, ecx: ecx, edx: edx }
+}
+
+// Ultima leaf estesa supportata (0x8000_0000 + n).
+pub fn max_extended_leaf() -> u32 {
+	cpuid(0x8000_0000).eax
+}
+
+// Execute Disable (NX): CPUID 0x8000_0001, EDX bit 20.
+pub fn has_no_execute() -> bool {
+	max_extended_leaf() >= 0x8000_0001 &&
+		cpuid(0x8000_0001).edx & (1 << 20) != 0
+}
+
+// TSC invariante: frequenza costante anche con P-state e C-state.
+// CPUID 0x8000_0007, EDX bit 8.
+pub fn has_invariant_tsc() -> bool {
+	max_extended_leaf() >= 0x8000_0007 &&
+		cpuid(0x8000_0007).edx & (1 << 8) != 0
+}
+
+// ---
+
+// TSC.
+
+// Legge il Time Stamp Counter. lfence impedisce che venga letto prima
+// delle istruzioni precedenti.
+pub fn rdtsc() -> u64 {
+	let (low, high): (u32, u32);
+	unsafe {
+		asm!("lfence; rdtsc"
+			 : "={eax}"(low), "={edx}"(high)
+			 :
+			 :
+			 : "volatile");
+	}
+	(high as u64) << 32 | low as u64
+}
+
+// ---
+
+// FEATURES.
+
+// Abilita le feature della CPU necessarie al paging del kernel.
+// Come i controlli in boot.asm, si ferma se la CPU non le supporta.
+pub fn init() {
+	if !has_no_execute() {
+		panic!("CPU does not support the NO_EXECUTE page flag.");
+	}
+
+	enable_nxe_bit();
+	enable_write_protect_bit();
+}
+
+// Setta EFER.NXE: senza, il bit 63 delle entry è riservato e provoca
+// un page fault.
+fn enable_nxe_bit() {
+	use x86::msr::{IA32_EFER, rdmsr, wrmsr};
+
+	let nxe_bit = 1 << 11;
+	unsafe {
+		let efer = rdmsr(IA32_EFER);
+		wrmsr(IA32_EFER, efer | nxe_bit);
+	}
+}
+
+// Setta CR0.WP: anche il kernel rispetta le pagine in sola lettura.
+fn enable_write_protect_bit() {
+	use x86::controlregs::{cr0, cr0_write};
+
+	let write_protect_bit = 1 << 16;
+	unsafe { cr0_write(cr0() | write_protect_bit) };
+}
+
+// ---
+
+// HALT.
+
+// Ferma la CPU fino al prossimo interrupt.
+pub fn halt() {
+	unsafe { asm!("hlt" :::: "volatile") };
+}
+
+// Riavvia con il reset del controller della tastiera. Se non funziona,
+// un'IDT vuota trasforma il breakpoint in un triple fault.
+pub fn reboot() -> ! {
+	use x86::io::{inb, outb};
+
+	unsafe {
+		asm!("cli" :::: "volatile");
+
+		while inb(0x64) & (1 << 1) != 0 {}
+		outb(0x64, 0xfe);
+
+		let empty_idt: [u16; 5] = [0; 5];
+		asm!("lidt ($0); int3" :: "r" (&empty_idt) : "memory" : "volatile");
+	}
+
+	loop {
+		halt();
+	}
+}
+
+// ---
@@ -0,0 +1,221 @@
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use acpi::Madt;
+use cpu;
+use memory::{self, WRITEABLE, NO_CACHE, NO_EXECUTE};
+use super::irq::{IRQ_BASE, IRQ_COUNT};
+use super::pic::CASCADE_IRQ;
+
+// Vettore degli interrupt spuri del local APIC: i 4 bit bassi devono
+// essere a uno sulle CPU più vecchie.
+pub const SPURIOUS_VECTOR: u8 = 0xff;
+
+const IA32_APIC_BASE: u32 = 0x1b;
+const APIC_BASE_ENABLE: u64 = 1 << 11;
+
+// Indirizzi virtuali dei registri, 0 finché l'APIC non è attivo.
+static LOCAL_APIC: AtomicUsize = AtomicUsize::new(0);
+static IO_APIC: AtomicUsize = AtomicUsize::new(0);
+
+// GSI da cui parte l'I/O APIC e GSI di ogni IRQ ISA dopo le override,
+// NO_GSI se l'IRQ non è collegata a nessuna linea.
+static IO_APIC_GSI_BASE: AtomicUsize = AtomicUsize::new(0);
+const NO_GSI: u32 = !0;
+static mut IRQ_GSI: [u32; IRQ_COUNT] = [NO_GSI; IRQ_COUNT];
+
+// APIC: CPUID 1, EDX bit 9.
+pub fn is_supported() -> bool {
+	cpu::cpuid(1).edx & (1 << 9) != 0
+}
+
+// LOCAL APIC.
+
+const LAPIC_ID: usize = 0x20;
+const LAPIC_TASK_PRIORITY: usize = 0x80;
+const LAPIC_EOI: usize = 0xb0;
+const LAPIC_SPURIOUS: usize = 0xf0;
+const LAPIC_LVT_LINT0: usize = 0x350;
+const LAPIC_LVT_LINT1: usize = 0x360;
+
+const LAPIC_SOFTWARE_ENABLE: u32 = 1 << 8;
+const LVT_MASKED: u32 = 1 << 16;
+
+fn init_local_apic(address: usize) {
+	use x86::msr::{rdmsr, wrmsr};
+
+	// Abilita l'APIC globalmente, all'indirizzo indicato dalla MADT.
+	unsafe {
+		let base = rdmsr(IA32_APIC_BASE) & 0xfff;
+		wrmsr(IA32_APIC_BASE, base | (address as u64 & !0xfff) | APIC_BASE_ENABLE);
+	}
+
+	let registers = memory::with_memory_controller(|controller| {
+		controller.map_physical(address, 0x1000, WRITEABLE | NO_CACHE | NO_EXECUTE)
+	});
+	LOCAL_APIC.store(registers, Ordering::SeqCst);
+
+	// Con i PIC disabilitati LINT0/LINT1 non servono.
+	write_local(LAPIC_LVT_LINT0, LVT_MASKED);
+	write_local(LAPIC_LVT_LINT1, LVT_MASKED);
+	write_local(LAPIC_TASK_PRIORITY, 0);
+	write_local(LAPIC_SPURIOUS, LAPIC_SOFTWARE_ENABLE | SPURIOUS_VECTOR as u32);
+}
+
+pub fn local_apic_id() -> u8 {
+	(read_local(LAPIC_ID) >> 24) as u8
+}
+
+pub fn end_of_interrupt() {
+	write_local(LAPIC_EOI, 0);
+}
+
+fn read_local(register: usize) -> u32 {
+	let base = LOCAL_APIC.load(Ordering::Relaxed);
+	unsafe { ptr::read_volatile((base + register) as *const u32) }
+}
+
+fn write_local(register: usize, value: u32) {
+	let base = LOCAL_APIC.load(Ordering::Relaxed);
+	unsafe { ptr::write_volatile((base + register) as *mut u32, value) };
+}
+
+// ---
+
+// I/O APIC.
+// Si accede ai registri scrivendo l'indice in IOREGSEL e leggendo
+// o scrivendo IOWIN.
+
+const IOREGSEL: usize = 0x00;
+const IOWIN: usize = 0x10;
+const IOAPIC_VERSION: u32 = 0x01;
+const IOAPIC_REDIRECTION_TABLE: u32 = 0x10;
+
+const REDIRECTION_ACTIVE_LOW: u64 = 1 << 13;
+const REDIRECTION_LEVEL_TRIGGERED: u64 = 1 << 15;
+const REDIRECTION_MASKED: u64 = 1 << 16;
+
+// Flag delle override della MADT: polarità (bit 0-1) e trigger (bit 2-3).
+const OVERRIDE_ACTIVE_LOW: u16 = 0b11;
+const OVERRIDE_LEVEL_TRIGGERED: u16 = 0b11 << 2;
+
+fn init_io_apic(madt: &Madt) {
+	let io_apic = &madt.io_apics[0];
+	let registers = memory::with_memory_controller(|controller| {
+		controller.map_physical(io_apic.address, 0x20, WRITEABLE | NO_CACHE | NO_EXECUTE)
+	});
+	IO_APIC.store(registers, Ordering::SeqCst);
+	IO_APIC_GSI_BASE.store(io_apic.gsi_base as usize, Ordering::SeqCst);
+
+	// Tutte le linee mascherate.
+	let entries = ((read_io(IOAPIC_VERSION) >> 16) & 0xff) + 1;
+	for entry in 0..entries {
+		write_redirection(entry, REDIRECTION_MASKED);
+	}
+
+	// GSI occupate dalle override: su q35 la IRQ 0 del PIT arriva sulla
+	// GSI 2, che non va riscritta con il vettore della IRQ 2.
+	let mut claimed = [false; IRQ_COUNT];
+	for over in madt.overrides.iter() {
+		if (over.gsi as usize) < IRQ_COUNT && over.irq as u32 != over.gsi {
+			claimed[over.gsi as usize] = true;
+		}
+	}
+
+	// Ogni IRQ ISA va sul vettore IRQ_BASE + irq, come con i PIC,
+	// verso il local APIC di questa CPU. Resta mascherata finché
+	// un driver non la registra.
+	let destination = (local_apic_id() as u64) << 56;
+	for irq in 0..IRQ_COUNT as u8 {
+		let mut gsi = irq as u32;
+		let mut entry = REDIRECTION_MASKED | destination | (IRQ_BASE + irq) as u64;
+
+		match madt.overrides.iter().find(|over| over.irq == irq) {
+			Some(over) => {
+				gsi = over.gsi;
+				if over.flags & OVERRIDE_ACTIVE_LOW == OVERRIDE_ACTIVE_LOW {
+					entry |= REDIRECTION_ACTIVE_LOW;
+				}
+				if over.flags & OVERRIDE_LEVEL_TRIGGERED == OVERRIDE_LEVEL_TRIGGERED {
+					entry |= REDIRECTION_LEVEL_TRIGGERED;
+				}
+			}
+			// La IRQ 2 è la cascata fra i PIC: con l'APIC non esiste.
+			None if irq == CASCADE_IRQ || claimed[irq as usize] => continue,
+			None => {}
+		}
+
+		if gsi < io_apic.gsi_base || gsi - io_apic.gsi_base >= entries {
+			continue;
+		}
+
+		unsafe { IRQ_GSI[irq as usize] = gsi };
+		write_redirection(gsi - io_apic.gsi_base, entry);
+	}
+}
+
+// Le IRQ senza linea restano sempre mascherate.
+pub fn mask(irq: u8) {
+	if let Some(entry) = redirection_entry(irq) {
+		let value = read_redirection(entry);
+		write_redirection(entry, value | REDIRECTION_MASKED);
+	}
+}
+
+pub fn unmask(irq: u8) {
+	if let Some(entry) = redirection_entry(irq) {
+		let value = read_redirection(entry);
+		write_redirection(entry, value & !REDIRECTION_MASKED);
+	}
+}
+
+fn redirection_entry(irq: u8) -> Option<u32> {
+	let gsi = unsafe { IRQ_GSI[irq as usize] };
+	if gsi == NO_GSI {
+		None
+	}
+	else {
+		Some(gsi - IO_APIC_GSI_BASE.load(Ordering::Relaxed) as u32)
+	}
+}
+
+fn read_redirection(entry: u32) -> u64 {
+	let register = IOAPIC_REDIRECTION_TABLE + entry * 2;
+	let low = read_io(register) as u64;
+	let high = read_io(register + 1) as u64;
+	low | (high << 32)
+}
+
+fn write_redirection(entry: u32, value: u64) {
+	let register = IOAPIC_REDIRECTION_TABLE + entry * 2;
+	write_io(register, value as u32);
+	write_io(register + 1, (value >> 32) as u32);
+}
+
+fn read_io(register: u32) -> u32 {
+	let base = IO_APIC.load(Ordering::Relaxed);
+	unsafe {
+		ptr::write_volatile((base + IOREGSEL) as *mut u32, register);
+		ptr::read_volatile((base + IOWIN) as *const u32)
+	}
+}
+
+fn write_io(register: u32, value: u32) {
+	let base = IO_APIC.load(Ordering::Relaxed);
+	unsafe {
+		ptr::write_volatile((base + IOREGSEL) as *mut u32, register);
+		ptr::write_volatile((base + IOWIN) as *mut u32, value);
+	}
+}
+
+// ---
+
+// INIT.
+
+// Attiva local APIC e I/O APIC secondo la MADT. Va chiamata dopo che i
+// PIC sono stati mascherati.
+pub fn init(madt: &Madt) {
+	init_local_apic(madt.local_apic_address);
+	init_io_apic(madt);
+}
+
+// ---
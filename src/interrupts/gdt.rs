@@ -0,0 +1,158 @@
+use core::mem::size_of;
+use super::idt::DescriptorTablePointer;
+
+const GDT_ENTRIES: usize = 8;
+
+// TASK STATE SEGMENT.
+// In long mode la TSS contiene solo gli stack: quelli per i cambi di
+// privilegio e la Interrupt Stack Table usata dagli interrupt.
+
+#[repr(C, packed)]
+pub struct TaskStateSegment {
+	reserved_1: u32,
+	pub privilege_stack_table: [u64; 3],
+	reserved_2: u64,
+	pub interrupt_stack_table: [u64; 7],
+	reserved_3: u64,
+	reserved_4: u16,
+	pub iomap_base: u16,
+}
+
+impl TaskStateSegment {
+	pub const fn new() -> TaskStateSegment {
+		TaskStateSegment {
+			reserved_1: 0,
+			privilege_stack_table: [0; 3],
+			reserved_2: 0,
+			interrupt_stack_table: [0; 7],
+			reserved_3: 0,
+			reserved_4: 0,
+			iomap_base: size_of::<TaskStateSegment>() as u16,
+		}
+	}
+}
+
+// ---
+
+// DESCRIPTORS.
+// Stessi segmenti di gdt64 in boot.asm, più il descrittore della TSS
+// che occupa due entry.
+
+const DESCRIPTOR_WRITEABLE: u64 = 1 << 41;
+const DESCRIPTOR_EXECUTABLE: u64 = 1 << 43;
+const DESCRIPTOR_USER_SEGMENT: u64 = 1 << 44;
+const DESCRIPTOR_PRESENT: u64 = 1 << 47;
+const DESCRIPTOR_LONG_MODE: u64 = 1 << 53;
+const DESCRIPTOR_TSS_AVAILABLE: u64 = 0b1001 << 40;
+
+pub enum Descriptor {
+	UserSegment(u64),
+	SystemSegment(u64, u64),
+}
+
+impl Descriptor {
+	pub fn kernel_code_segment() -> Descriptor {
+		Descriptor::UserSegment(DESCRIPTOR_USER_SEGMENT | DESCRIPTOR_PRESENT |
+								DESCRIPTOR_WRITEABLE | DESCRIPTOR_EXECUTABLE |
+								DESCRIPTOR_LONG_MODE)
+	}
+
+	pub fn kernel_data_segment() -> Descriptor {
+		Descriptor::UserSegment(DESCRIPTOR_USER_SEGMENT | DESCRIPTOR_PRESENT |
+								DESCRIPTOR_WRITEABLE)
+	}
+
+	pub fn tss_segment(tss: &'static TaskStateSegment) -> Descriptor {
+		let base = tss as *const _ as u64;
+		let limit = (size_of::<TaskStateSegment>() - 1) as u64;
+
+		let mut low = DESCRIPTOR_PRESENT | DESCRIPTOR_TSS_AVAILABLE;
+		low |= limit & 0xffff;
+		low |= (base & 0xff_ffff) << 16;
+		low |= ((base >> 24) & 0xff) << 56;
+
+		let high = base >> 32;
+
+		Descriptor::SystemSegment(low, high)
+	}
+}
+
+// ---
+
+// GDT.
+
+pub struct Gdt {
+	table: [u64; GDT_ENTRIES],
+	next_free: usize,
+}
+
+impl Gdt {
+	pub const fn new() -> Gdt {
+		Gdt {
+			table: [0; GDT_ENTRIES],
+			next_free: 1,	// La entry 0 resta nulla.
+		}
+	}
+
+	// Aggiunge il descrittore e ne restituisce il selettore.
+	pub fn add_entry(&mut self, entry: Descriptor) -> u16 {
+		let index = match entry {
+			Descriptor::UserSegment(value) => self.push(value),
+			Descriptor::SystemSegment(low, high) => {
+				let index = self.push(low);
+				self.push(high);
+				index
+			}
+		};
+		(index * 8) as u16
+	}
+
+	fn push(&mut self, value: u64) -> usize {
+		assert! (self.next_free < GDT_ENTRIES, "GDT full.");
+
+		let index = self.next_free;
+		self.table[index] = value;
+		self.next_free += 1;
+		index
+	}
+
+	pub fn load(&'static self) {
+		let pointer = DescriptorTablePointer {
+			limit: (self.table.len() * size_of::<u64>() - 1) as u16,
+			base: self.table.as_ptr() as u64,
+		};
+
+		unsafe { asm!("lgdt ($0)" :: "r" (&pointer) : "memory") };
+	}
+}
+
+// ---
+
+// SEGMENT REGISTERS.
+
+// Ricarica CS con un far return verso l'istruzione successiva.
+pub unsafe fn set_cs(selector: u16) {
+	asm!("pushq $0
+		  leaq 1f(%rip), %rax
+		  pushq %rax
+		  lretq
+		  1:"
+		 :
+		 : "ri" (selector as u64)
+		 : "rax" "memory");
+}
+
+pub unsafe fn load_data_segments(selector: u16) {
+	asm!("mov $0, %ds
+		  mov $0, %es
+		  mov $0, %ss"
+		 :
+		 : "r" (selector)
+		 : "memory");
+}
+
+pub unsafe fn load_tss(selector: u16) {
+	asm!("ltr $0" :: "r" (selector));
+}
+
+// ---
@@ -0,0 +1,133 @@
+use core::mem::size_of;
+
+const IDT_ENTRIES: usize = 256;
+
+// ENTRY OPTIONS.
+// Bit 0-2: indice nella Interrupt Stack Table (0 = stack corrente).
+// Bit 8-11: tipo di gate, bit 13-14: privilegio, bit 15: presente.
+
+#[derive(Clone, Copy)]
+pub struct EntryOptions(u16);
+
+impl EntryOptions {
+	// Interrupt gate (interrupt disabilitati durante l'handler), non presente.
+	const fn minimal() -> EntryOptions {
+		EntryOptions(0b1110_0000_0000)
+	}
+
+	pub fn set_present(&mut self, present: bool) -> &mut EntryOptions {
+		self.set_bit(15, present);
+		self
+	}
+
+	pub fn disable_interrupts(&mut self, disable: bool) -> &mut EntryOptions {
+		// Trap gate se gli interrupt restano abilitati.
+		self.set_bit(8, !disable);
+		self
+	}
+
+	pub fn set_privilege_level(&mut self, dpl: u16) -> &mut EntryOptions {
+		self.0 = (self.0 & !(0b11 << 13)) | ((dpl & 0b11) << 13);
+		self
+	}
+
+	// `index` parte da 0, come gli stack della TSS; 0 nella entry
+	// significa "nessuno stack dedicato".
+	pub fn set_stack_index(&mut self, index: u16) -> &mut EntryOptions {
+		self.0 = (self.0 & !0b111) | ((index + 1) & 0b111);
+		self
+	}
+
+	fn set_bit(&mut self, bit: u16, value: bool) {
+		if value {
+			self.0 |= 1 << bit;
+		}
+		else {
+			self.0 &= !(1 << bit);
+		}
+	}
+}
+
+// ---
+
+// ENTRY.
+
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+pub struct Entry {
+	pointer_low: u16,
+	gdt_selector: u16,
+	options: EntryOptions,
+	pointer_middle: u16,
+	pointer_high: u32,
+	reserved: u32,
+}
+
+impl Entry {
+	const fn missing() -> Entry {
+		Entry {
+			pointer_low: 0,
+			gdt_selector: 0,
+			options: EntryOptions::minimal(),
+			pointer_middle: 0,
+			pointer_high: 0,
+			reserved: 0,
+		}
+	}
+
+	fn new(gdt_selector: u16, handler: u64) -> Entry {
+		let mut options = EntryOptions::minimal();
+		options.set_present(true).disable_interrupts(true);
+
+		Entry {
+			pointer_low: handler as u16,
+			gdt_selector: gdt_selector,
+			options: options,
+			pointer_middle: (handler >> 16) as u16,
+			pointer_high: (handler >> 32) as u32,
+			reserved: 0,
+		}
+	}
+}
+
+// ---
+
+// IDT.
+
+pub struct Idt([Entry; IDT_ENTRIES]);
+
+impl Idt {
+	pub const fn new() -> Idt {
+		Idt([Entry::missing(); IDT_ENTRIES])
+	}
+
+	// Imposta l'handler del vettore e ne restituisce le opzioni.
+	pub fn set_handler(&mut self, vector: u8, handler: u64) -> &mut EntryOptions {
+		self.0[vector as usize] = Entry::new(code_segment(), handler);
+		&mut self.0[vector as usize].options
+	}
+
+	pub fn load(&'static self) {
+		let pointer = DescriptorTablePointer {
+			limit: (size_of::<Self>() - 1) as u16,
+			base: self as *const _ as u64,
+		};
+
+		unsafe { asm!("lidt ($0)" :: "r" (&pointer) : "memory") };
+	}
+}
+
+// ---
+
+#[repr(C, packed)]
+pub struct DescriptorTablePointer {
+	pub limit: u16,
+	pub base: u64,
+}
+
+// Selettore del segmento di codice corrente.
+pub fn code_segment() -> u16 {
+	let segment: u16;
+	unsafe { asm!("mov %cs, $0" : "=r" (segment)) };
+	segment
+}
@@ -0,0 +1,148 @@
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use spin::Mutex;
+use acpi;
+use super::{apic, pic, without_interrupts};
+
+// Primo vettore delle IRQ, subito dopo le eccezioni della CPU.
+pub const IRQ_BASE: u8 = 32;
+pub const IRQ_COUNT: usize = 16;
+
+pub type IrqHandler = fn(irq: u8);
+
+static HANDLERS: Mutex<[Option<IrqHandler>; IRQ_COUNT]> = Mutex::new([None; IRQ_COUNT]);
+
+static SPURIOUS_IRQS: AtomicUsize = AtomicUsize::new(0);
+static UNHANDLED_IRQS: AtomicUsize = AtomicUsize::new(0);
+
+// Le IRQ passano dall'I/O APIC invece che dai PIC.
+static APIC_MODE: AtomicBool = AtomicBool::new(false);
+
+// INIT.
+
+pub fn init() {
+	pic::remap(IRQ_BASE, IRQ_BASE + 8);
+}
+
+// Passa all'APIC se la CPU ha un local APIC e la MADT descrive almeno
+// un I/O APIC, altrimenti restano i PIC. Richiede il MemoryController
+// per mappare i registri.
+pub fn init_apic() {
+	let madt = if apic::is_supported() { acpi::find_madt() } else { None };
+	let madt = match madt {
+		Some(ref madt) if !madt.io_apics.is_empty() => madt,
+		_ => {
+			log! ("APIC not available, using the legacy PICs.");
+			return;
+		}
+	};
+
+	without_interrupts(|| {
+		// I PIC si rimappano comunque (in init) perché una loro IRQ
+		// spuria non finisca sui vettori delle eccezioni.
+		pic::disable();
+		apic::init(madt);
+		APIC_MODE.store(true, Ordering::SeqCst);
+
+		// Le linee già registrate restano attive anche con l'APIC.
+		let handlers = HANDLERS.lock();
+		for irq in 0..IRQ_COUNT {
+			if handlers[irq].is_some() {
+				apic::unmask(irq as u8);
+			}
+		}
+	});
+
+	log! ("APIC enabled: local APIC {}, {} I/O APIC(s), {} override(s).",
+		  apic::local_apic_id(), madt.io_apics.len(), madt.overrides.len());
+}
+
+pub fn is_apic_mode() -> bool {
+	APIC_MODE.load(Ordering::Relaxed)
+}
+
+// ---
+
+// REGISTRY.
+
+// Registra l'handler della linea e la smaschera.
+// Un driver per linea: registrare di nuovo sostituisce l'handler.
+pub fn register(irq: u8, handler: IrqHandler) {
+	assert! ((irq as usize) < IRQ_COUNT, "Invalid IRQ {}.", irq);
+
+	// Con il lock preso, una IRQ arrivata ora bloccherebbe dispatch.
+	without_interrupts(|| {
+		HANDLERS.lock()[irq as usize] = Some(handler);
+		unmask_line(irq);
+	});
+}
+
+pub fn unregister(irq: u8) {
+	assert! ((irq as usize) < IRQ_COUNT, "Invalid IRQ {}.", irq);
+
+	without_interrupts(|| {
+		mask_line(irq);
+		HANDLERS.lock()[irq as usize] = None;
+	});
+}
+
+pub fn mask(irq: u8) {
+	without_interrupts(|| mask_line(irq));
+}
+
+pub fn unmask(irq: u8) {
+	without_interrupts(|| unmask_line(irq));
+}
+
+fn mask_line(irq: u8) {
+	if is_apic_mode() { apic::mask(irq) } else { pic::mask(irq) }
+}
+
+fn unmask_line(irq: u8) {
+	if is_apic_mode() { apic::unmask(irq) } else { pic::unmask(irq) }
+}
+
+pub fn spurious_count() -> usize {
+	SPURIOUS_IRQS.load(Ordering::Relaxed)
+}
+
+// Spuri del local APIC: non vogliono EOI.
+pub fn spurious_apic_interrupt() {
+	SPURIOUS_IRQS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn unhandled_count() -> usize {
+	UNHANDLED_IRQS.load(Ordering::Relaxed)
+}
+
+// ---
+
+// DISPATCH.
+
+// Chiamata da interrupt_dispatch per i vettori IRQ_BASE..IRQ_BASE + 16,
+// con gli interrupt disabilitati.
+pub fn dispatch(irq: u8) {
+	let apic_mode = is_apic_mode();
+	if !apic_mode && pic::is_spurious(irq) {
+		SPURIOUS_IRQS.fetch_add(1, Ordering::Relaxed);
+		pic::end_of_spurious_interrupt(irq);
+		return;
+	}
+
+	// Copia l'handler per non tenere il lock durante la chiamata.
+	let handler = HANDLERS.lock()[irq as usize];
+	match handler {
+		Some(handler) => handler(irq),
+		None => {
+			UNHANDLED_IRQS.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+
+	if apic_mode {
+		apic::end_of_interrupt();
+	}
+	else {
+		pic::end_of_interrupt(irq);
+	}
+}
+
+// ---
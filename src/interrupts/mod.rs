@@ -0,0 +1,257 @@
+use core::fmt;
+use spin::Mutex;
+
+use self::idt::Idt;
+use self::gdt::{Gdt, Descriptor, TaskStateSegment};
+
+mod idt;
+mod gdt;
+mod page_fault;
+mod pic;
+mod apic;
+pub mod irq;
+
+// Puntatori agli stub di interrupts.asm, uno per vettore.
+extern {
+	static isr_stub_table: [u64; 256];
+}
+
+static IDT: Mutex<Idt> = Mutex::new(Idt::new());
+
+// GDT e TSS vengono scritte una sola volta da init, prima di essere caricate.
+static mut GDT: Gdt = Gdt::new();
+static mut TSS: TaskStateSegment = TaskStateSegment::new();
+
+// Indici nella Interrupt Stack Table degli stack dedicati.
+const DOUBLE_FAULT_IST_INDEX: usize = 0;
+const IST_STACK_SIZE: usize = 4096 * 4;
+
+// Stack dedicato al double fault: uno stack overflow lascia lo stack del
+// kernel inutilizzabile, quindi l'handler non può girare su quello.
+static mut DOUBLE_FAULT_STACK: [u8; IST_STACK_SIZE] = [0; IST_STACK_SIZE];
+
+// INIT.
+
+pub fn init() {
+	init_gdt();
+
+	let mut idt = IDT.lock();
+	for vector in 0..256 {
+		let handler = unsafe { isr_stub_table[vector] };
+		let options = idt.set_handler(vector as u8, handler);
+
+		if vector as u64 == DOUBLE_FAULT {
+			options.set_stack_index(DOUBLE_FAULT_IST_INDEX as u16);
+		}
+	}
+
+	// L'IDT è statica: il suo indirizzo resta valido dopo il lock.
+	let idt: &'static Idt = unsafe { &*(&*idt as *const Idt) };
+	idt.load();
+	
+	// IRQ rimappate ma tutte mascherate: i driver le abilitano
+	// registrando un handler con irq::register.
+	irq::init();
+}
+
+// Da chiamare dopo memory::init: l'APIC va mappato nella finestra fisica.
+pub fn init_apic() {
+	irq::init_apic();
+}
+
+// Sostituisce la GDT di boot.asm con una che contiene anche la TSS.
+// Il segmento di codice resta allo stesso selettore.
+fn init_gdt() {
+	unsafe {
+		TSS.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX] = stack_top(&DOUBLE_FAULT_STACK);
+
+		let code_selector = GDT.add_entry(Descriptor::kernel_code_segment());
+		let data_selector = GDT.add_entry(Descriptor::kernel_data_segment());
+		let tss_selector = GDT.add_entry(Descriptor::tss_segment(&TSS));
+		GDT.load();
+
+		gdt::set_cs(code_selector);
+		gdt::load_data_segments(data_selector);
+		gdt::load_tss(tss_selector);
+	}
+}
+
+// Cima dello stack, allineata a 16 byte.
+fn stack_top(stack: &'static [u8; IST_STACK_SIZE]) -> u64 {
+	let top = stack as *const _ as usize + IST_STACK_SIZE;
+	(top & !0xf) as u64
+}
+
+// ---
+
+// INTERRUPT FLAG.
+
+pub fn enable() {
+	unsafe { asm!("sti" :::: "volatile") };
+}
+
+pub fn disable() {
+	unsafe { asm!("cli" :::: "volatile") };
+}
+
+pub fn are_enabled() -> bool {
+	let flags: u64;
+	unsafe { asm!("pushfq; popq $0" : "=r" (flags) ::: "volatile") };
+	flags & (1 << 9) != 0
+}
+
+// Esegue `f` con gli interrupt disabilitati, poi ripristina lo stato
+// precedente.
+pub fn without_interrupts<F, T>(f: F) -> T
+	where F: FnOnce() -> T
+{
+	let enabled = are_enabled();
+	if enabled {
+		disable();
+	}
+
+	let result = f();
+
+	if enabled {
+		enable();
+	}
+	result
+}
+
+// ---
+
+// INTERRUPT CONTEXT.
+// Layout dello stack costruito da isr_common: registri scratch salvati,
+// vettore, error code e frame salvato dalla CPU.
+
+#[repr(C)]
+pub struct InterruptContext {
+	pub r11: u64,
+	pub r10: u64,
+	pub r9: u64,
+	pub r8: u64,
+	pub rdi: u64,
+	pub rsi: u64,
+	pub rdx: u64,
+	pub rcx: u64,
+	pub rax: u64,
+	pub vector: u64,
+	pub error_code: u64,
+	pub stack_frame: InterruptStackFrame,
+}
+
+#[repr(C)]
+pub struct InterruptStackFrame {
+	pub instruction_pointer: u64,
+	pub code_segment: u64,
+	pub cpu_flags: u64,
+	pub stack_pointer: u64,
+	pub stack_segment: u64,
+}
+
+impl fmt::Debug for InterruptStackFrame {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "RIP: {:#x}, CS: {:#x}, RFLAGS: {:#x}, RSP: {:#x}, SS: {:#x}",
+			   self.instruction_pointer,
+			   self.code_segment,
+			   self.cpu_flags,
+			   self.stack_pointer,
+			   self.stack_segment)
+	}
+}
+
+// ---
+
+// EXCEPTIONS.
+
+const BREAKPOINT: u64 = 3;
+const DOUBLE_FAULT: u64 = 8;
+const PAGE_FAULT: u64 = 14;
+
+static EXCEPTION_NAMES: [&'static str; 32] = [
+	"Divide Error",
+	"Debug",
+	"Non-Maskable Interrupt",
+	"Breakpoint",
+	"Overflow",
+	"Bound Range Exceeded",
+	"Invalid Opcode",
+	"Device Not Available",
+	"Double Fault",
+	"Coprocessor Segment Overrun",
+	"Invalid TSS",
+	"Segment Not Present",
+	"Stack-Segment Fault",
+	"General Protection Fault",
+	"Page Fault",
+	"Reserved",
+	"x87 Floating-Point Exception",
+	"Alignment Check",
+	"Machine Check",
+	"SIMD Floating-Point Exception",
+	"Virtualization Exception",
+	"Control Protection Exception",
+	"Reserved",
+	"Reserved",
+	"Reserved",
+	"Reserved",
+	"Reserved",
+	"Reserved",
+	"Hypervisor Injection Exception",
+	"VMM Communication Exception",
+	"Security Exception",
+	"Reserved",
+];
+
+// Chiamata da isr_common per ogni interrupt.
+#[no_mangle]
+pub extern "C" fn interrupt_dispatch(context: &mut InterruptContext) {
+	match context.vector {
+		BREAKPOINT => breakpoint_handler(context),
+		DOUBLE_FAULT => double_fault_handler(context),
+		PAGE_FAULT => page_fault::page_fault_handler(context),
+		0...31 => exception_handler(context),
+		32...47 => irq::dispatch((context.vector - irq::IRQ_BASE as u64) as u8),
+		vector if vector == apic::SPURIOUS_VECTOR as u64 => irq::spurious_apic_interrupt(),
+		vector => panic!("Unexpected interrupt {}.", vector),
+	}
+}
+
+// Il panic handler pulisce lo schermo: le eccezioni fatali mettono
+// tutto il report nel messaggio del panic.
+struct ExceptionReport<'a>(&'a InterruptContext);
+
+impl<'a> fmt::Display for ExceptionReport<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let context = self.0;
+		try!(writeln!(f, "EXCEPTION: {} (vector {}), error code: {:#x}",
+					  EXCEPTION_NAMES[context.vector as usize],
+					  context.vector,
+					  context.error_code));
+		write!(f, "{:?}", context.stack_frame)
+	}
+}
+
+// Dopo un breakpoint si può riprendere dall'istruzione successiva.
+fn breakpoint_handler(context: &mut InterruptContext) {
+	println!("");
+	println!("{}", ExceptionReport(context));
+}
+
+// Gira sullo stack dedicato della IST. CR2 contiene l'indirizzo
+// dell'eventuale page fault che l'ha provocato, ad esempio un accesso
+// alla guard page dopo uno stack overflow.
+fn double_fault_handler(context: &mut InterruptContext) {
+	use x86::controlregs::cr2;
+
+	panic!("{}\nCR2: {:#x}",
+		   ExceptionReport(context),
+		   unsafe { cr2() });
+}
+
+// Le altre eccezioni non sono recuperabili.
+fn exception_handler(context: &mut InterruptContext) {
+	panic!("{}", ExceptionReport(context));
+}
+
+// ---
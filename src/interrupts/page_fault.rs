@@ -0,0 +1,63 @@
+use core::fmt;
+use memory::PageWalk;
+use super::{InterruptContext, InterruptStackFrame};
+
+// ERROR CODE.
+
+bitflags! {
+	flags PageFaultErrorCode: u64 {
+		const PROTECTION_VIOLATION = 1 << 0,
+		const CAUSED_BY_WRITE = 1 << 1,
+		const USER_MODE = 1 << 2,
+		const MALFORMED_TABLE = 1 << 3,
+		const INSTRUCTION_FETCH = 1 << 4,
+	}
+}
+
+impl fmt::Display for PageFaultErrorCode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}, {}, {} mode{}",
+			   if self.contains(PROTECTION_VIOLATION) { "protection violation" }
+			   else { "page not present" },
+			   if self.contains(INSTRUCTION_FETCH) { "instruction fetch" }
+			   else if self.contains(CAUSED_BY_WRITE) { "write" }
+			   else { "read" },
+			   if self.contains(USER_MODE) { "user" } else { "kernel" },
+			   if self.contains(MALFORMED_TABLE) { ", reserved bit set" } else { "" })
+	}
+}
+
+// ---
+
+// PAGE FAULT REPORT.
+// Come ExceptionReport (interrupts/mod.rs), formattato nel messaggio del panic.
+
+struct PageFaultReport<'a> {
+	address: usize,
+	error_code: PageFaultErrorCode,
+	stack_frame: &'a InterruptStackFrame,
+}
+
+impl<'a> fmt::Display for PageFaultReport<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(writeln!(f, "PAGE FAULT at {:#x}", self.address));
+		try!(writeln!(f, "Error code: {:#x} ({})", self.error_code.bits(), self.error_code));
+		try!(writeln!(f, "Flags: {:?}", self.error_code));
+		try!(writeln!(f, "{:?}", self.stack_frame));
+		write!(f, "{}", PageWalk::new(self.address))
+	}
+}
+
+// ---
+
+pub fn page_fault_handler(context: &mut InterruptContext) {
+	use x86::controlregs::cr2;
+
+	let report = PageFaultReport {
+		address: unsafe { cr2() } as usize,
+		error_code: PageFaultErrorCode::from_bits_truncate(context.error_code),
+		stack_frame: &context.stack_frame,
+	};
+
+	panic!("{}", report);
+}
@@ -0,0 +1,128 @@
+use x86::io::{inb, outb};
+
+// PIC 8259.
+// Due PIC in cascata: il master gestisce le IRQ 0-7, lo slave le IRQ 8-15
+// ed è collegato alla IRQ 2 del master.
+
+const MASTER_COMMAND: u16 = 0x20;
+const MASTER_DATA: u16 = 0x21;
+const SLAVE_COMMAND: u16 = 0xa0;
+const SLAVE_DATA: u16 = 0xa1;
+
+const ICW1_INIT: u8 = 0x10;
+const ICW1_ICW4: u8 = 0x01;
+const ICW4_8086: u8 = 0x01;
+const OCW3_READ_ISR: u8 = 0x0b;
+const END_OF_INTERRUPT: u8 = 0x20;
+
+// IRQ del master a cui è collegato lo slave.
+pub const CASCADE_IRQ: u8 = 2;
+
+// Sposta le IRQ sui vettori master_offset..master_offset + 16, per non
+// sovrapporle alle eccezioni della CPU (vettori 0-31).
+// Tutte le IRQ restano mascherate tranne la cascata verso lo slave.
+pub fn remap(master_offset: u8, slave_offset: u8) {
+	unsafe {
+		outb(MASTER_COMMAND, ICW1_INIT | ICW1_ICW4);
+		io_wait();
+		outb(SLAVE_COMMAND, ICW1_INIT | ICW1_ICW4);
+		io_wait();
+
+		outb(MASTER_DATA, master_offset);
+		io_wait();
+		outb(SLAVE_DATA, slave_offset);
+		io_wait();
+
+		// Il master ha lo slave sulla IRQ 2, lo slave ha identità 2.
+		outb(MASTER_DATA, 1 << CASCADE_IRQ);
+		io_wait();
+		outb(SLAVE_DATA, CASCADE_IRQ);
+		io_wait();
+
+		outb(MASTER_DATA, ICW4_8086);
+		io_wait();
+		outb(SLAVE_DATA, ICW4_8086);
+		io_wait();
+
+		outb(MASTER_DATA, !(1 << CASCADE_IRQ));
+		outb(SLAVE_DATA, 0xff);
+	}
+}
+
+// Maschera tutte le IRQ, ad esempio quando si passa all'APIC.
+pub fn disable() {
+	unsafe {
+		outb(MASTER_DATA, 0xff);
+		outb(SLAVE_DATA, 0xff);
+	}
+}
+
+pub fn mask(irq: u8) {
+	let (port, bit) = data_port_and_bit(irq);
+	unsafe { outb(port, inb(port) | (1 << bit)) };
+}
+
+pub fn unmask(irq: u8) {
+	let (port, bit) = data_port_and_bit(irq);
+	unsafe { outb(port, inb(port) & !(1 << bit)) };
+}
+
+pub fn is_masked(irq: u8) -> bool {
+	let (port, bit) = data_port_and_bit(irq);
+	unsafe { inb(port) & (1 << bit) != 0 }
+}
+
+// Le IRQ 7 e 15 possono arrivare anche quando nessun dispositivo le ha
+// alzate (rumore sulla linea, IRQ ritirata): in quel caso il bit
+// corrispondente nell'In-Service Register è a zero.
+pub fn is_spurious(irq: u8) -> bool {
+	match irq {
+		7 => read_isr(MASTER_COMMAND) & (1 << 7) == 0,
+		15 => read_isr(SLAVE_COMMAND) & (1 << 7) == 0,
+		_ => false,
+	}
+}
+
+// Segnala la fine dell'interrupt. Lo slave vuole l'EOI per le proprie IRQ,
+// il master sempre, perché anche la cascata è in servizio.
+pub fn end_of_interrupt(irq: u8) {
+	unsafe {
+		if irq >= 8 {
+			outb(SLAVE_COMMAND, END_OF_INTERRUPT);
+		}
+		outb(MASTER_COMMAND, END_OF_INTERRUPT);
+	}
+}
+
+// Una IRQ 15 spuria è comunque passata dalla cascata del master,
+// che va chiusa con un EOI.
+pub fn end_of_spurious_interrupt(irq: u8) {
+	if irq >= 8 {
+		unsafe { outb(MASTER_COMMAND, END_OF_INTERRUPT) };
+	}
+}
+
+fn data_port_and_bit(irq: u8) -> (u16, u8) {
+	assert! (irq < 16, "Invalid IRQ {}.", irq);
+
+	if irq < 8 {
+		(MASTER_DATA, irq)
+	}
+	else {
+		(SLAVE_DATA, irq - 8)
+	}
+}
+
+fn read_isr(command_port: u16) -> u8 {
+	unsafe {
+		outb(command_port, OCW3_READ_ISR);
+		inb(command_port)
+	}
+}
+
+// Scrive su una porta inutilizzata per dare tempo ai PIC di rispondere.
+fn io_wait() {
+	unsafe { outb(0x80, 0) };
+}
+
+// ---
@@ -0,0 +1,195 @@
+use super::{KeyCode, Modifiers, CAPS_LOCK, NUM_LOCK, RIGHT_ALT};
+
+// LAYOUT.
+// Traduce un tasto fisico, con i modificatori attivi, nel carattere che
+// porta scritto sulla tastiera di quel paese.
+
+pub enum KeyOutput {
+	Nothing,
+	Char(char),
+	// Accento che si combina con il tasto successivo.
+	DeadKey(char),
+}
+
+pub trait KeyboardLayout: Sync {
+	fn name(&self) -> &'static str;
+
+	// Chiamata solo per le pressioni, senza Ctrl e Alt sinistro.
+	fn map_key(&self, code: KeyCode, modifiers: Modifiers) -> KeyOutput;
+}
+
+pub static US: Us = Us;
+pub static ITALIAN: Italian = Italian;
+
+static LAYOUTS: [&'static KeyboardLayout; 2] = [&US, &ITALIAN];
+
+pub fn by_name(name: &str) -> Option<&'static KeyboardLayout> {
+	LAYOUTS.iter().cloned().find(|layout| layout.name() == name)
+}
+
+// ---
+
+// US QWERTY.
+
+pub struct Us;
+
+impl KeyboardLayout for Us {
+	fn name(&self) -> &'static str {
+		"us"
+	}
+
+	fn map_key(&self, code: KeyCode, modifiers: Modifiers) -> KeyOutput {
+		use super::KeyCode::*;
+
+		// Niente AltGr sulla tastiera US.
+		if modifiers.contains(RIGHT_ALT) {
+			return KeyOutput::Nothing;
+		}
+
+		let (normal, shifted) = match code {
+			Key1 => ('1', '!'), Key2 => ('2', '@'), Key3 => ('3', '#'),
+			Key4 => ('4', '$'), Key5 => ('5', '%'), Key6 => ('6', '^'),
+			Key7 => ('7', '&'), Key8 => ('8', '*'), Key9 => ('9', '('),
+			Key0 => ('0', ')'),
+			Minus => ('-', '_'), Equals => ('=', '+'),
+			LeftBracket => ('[', '{'), RightBracket => (']', '}'),
+			Semicolon => (';', ':'), Quote => ('\'', '"'), Backtick => ('`', '~'),
+			Backslash | NonUsBackslash => ('\\', '|'),
+			Comma => (',', '<'), Period => ('.', '>'), Slash => ('/', '?'),
+			_ => return common_key(code, modifiers),
+		};
+
+		KeyOutput::Char(if modifiers.shift() { shifted } else { normal })
+	}
+}
+
+// ---
+
+// ITALIANO.
+// Tastiera ISO italiana. Con AltGr: @ # [ ] { } €, più due tasti
+// morti per gli accenti che mancano (` e ~).
+
+pub struct Italian;
+
+impl KeyboardLayout for Italian {
+	fn name(&self) -> &'static str {
+		"it"
+	}
+
+	fn map_key(&self, code: KeyCode, modifiers: Modifiers) -> KeyOutput {
+		use super::KeyCode::*;
+
+		if modifiers.contains(RIGHT_ALT) {
+			let shift = modifiers.shift();
+			return match code {
+				LeftBracket => KeyOutput::Char(if shift { '{' } else { '[' }),
+				RightBracket => KeyOutput::Char(if shift { '}' } else { ']' }),
+				Semicolon => KeyOutput::Char('@'),
+				Quote => KeyOutput::Char('#'),
+				E => KeyOutput::Char('€'),
+				Minus => KeyOutput::DeadKey('`'),
+				Equals => KeyOutput::DeadKey('~'),
+				_ => KeyOutput::Nothing,
+			};
+		}
+
+		let (normal, shifted) = match code {
+			Backtick => ('\\', '|'),
+			Key1 => ('1', '!'), Key2 => ('2', '"'), Key3 => ('3', '£'),
+			Key4 => ('4', '$'), Key5 => ('5', '%'), Key6 => ('6', '&'),
+			Key7 => ('7', '/'), Key8 => ('8', '('), Key9 => ('9', ')'),
+			Key0 => ('0', '='),
+			Minus => ('\'', '?'), Equals => ('ì', '^'),
+			LeftBracket => ('è', 'é'), RightBracket => ('+', '*'),
+			Semicolon => ('ò', 'ç'), Quote => ('à', '°'), Backslash => ('ù', '§'),
+			NonUsBackslash => ('<', '>'),
+			Comma => (',', ';'), Period => ('.', ':'), Slash => ('-', '_'),
+			_ => return common_key(code, modifiers),
+		};
+
+		KeyOutput::Char(if modifiers.shift() { shifted } else { normal })
+	}
+}
+
+// ---
+
+// TASTI COMUNI.
+// Lettere, tastierino e tasti di controllo sono uguali ovunque.
+
+fn common_key(code: KeyCode, modifiers: Modifiers) -> KeyOutput {
+	use super::KeyCode::*;
+
+	let character = match code {
+		Space => ' ',
+		Tab => '\t',
+		Enter | KeypadEnter => '\n',
+		Backspace => '\x08',
+		KeypadStar => '*',
+		KeypadMinus => '-',
+		KeypadPlus => '+',
+		KeypadSlash => '/',
+		_ => match keypad_digit(code, modifiers).or_else(|| letter(code, modifiers)) {
+			Some(character) => character,
+			None => return KeyOutput::Nothing,
+		},
+	};
+
+	KeyOutput::Char(character)
+}
+
+// Le lettere dipendono anche da CapsLock.
+fn letter(code: KeyCode, modifiers: Modifiers) -> Option<char> {
+	use super::KeyCode::*;
+
+	let letter = match code {
+		A => 'a', B => 'b', C => 'c', D => 'd', E => 'e', F => 'f', G => 'g',
+		H => 'h', I => 'i', J => 'j', K => 'k', L => 'l', M => 'm', N => 'n',
+		O => 'o', P => 'p', Q => 'q', R => 'r', S => 's', T => 't', U => 'u',
+		V => 'v', W => 'w', X => 'x', Y => 'y', Z => 'z',
+		_ => return None,
+	};
+
+	if modifiers.shift() != modifiers.contains(CAPS_LOCK) {
+		Some(((letter as u8) - b'a' + b'A') as char)
+	}
+	else {
+		Some(letter)
+	}
+}
+
+// Senza NumLock il tastierino numerico fa da tasti di navigazione.
+fn keypad_digit(code: KeyCode, modifiers: Modifiers) -> Option<char> {
+	use super::KeyCode::*;
+
+	if !modifiers.contains(NUM_LOCK) {
+		return None;
+	}
+
+	Some(match code {
+		Keypad0 => '0', Keypad1 => '1', Keypad2 => '2', Keypad3 => '3',
+		Keypad4 => '4', Keypad5 => '5', Keypad6 => '6', Keypad7 => '7',
+		Keypad8 => '8', Keypad9 => '9', KeypadPeriod => '.',
+		_ => return None,
+	})
+}
+
+// ---
+
+// TASTI MORTI.
+
+// Accento seguito da una lettera. Con lo spazio si ottiene l'accento da
+// solo; con un altro tasto l'accento si perde.
+pub fn compose(accent: char, character: char) -> char {
+	match (accent, character) {
+		('`', 'a') => 'à', ('`', 'e') => 'è', ('`', 'i') => 'ì',
+		('`', 'o') => 'ò', ('`', 'u') => 'ù',
+		('`', 'A') => 'À', ('`', 'E') => 'È', ('`', 'I') => 'Ì',
+		('`', 'O') => 'Ò', ('`', 'U') => 'Ù',
+		('~', 'a') => 'ã', ('~', 'o') => 'õ', ('~', 'n') => 'ñ',
+		('~', 'A') => 'Ã', ('~', 'O') => 'Õ', ('~', 'N') => 'Ñ',
+		(accent, ' ') => accent,
+		(_, character) => character,
+	}
+}
+
+// ---
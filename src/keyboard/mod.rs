@@ -0,0 +1,363 @@
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use spin::Mutex;
+use x86::io::{inb, outb};
+use interrupts::{self, irq};
+use vga_buffer;
+use self::layout::KeyOutput;
+
+pub use self::scancode::KeyCode;
+pub use self::layout::KeyboardLayout;
+
+mod scancode;
+pub mod layout;
+
+// CONTROLLER PS/2.
+
+const DATA_PORT: u16 = 0x60;
+const STATUS_PORT: u16 = 0x64;
+
+const STATUS_OUTPUT_FULL: u8 = 1 << 0;
+const STATUS_INPUT_FULL: u8 = 1 << 1;
+
+const COMMAND_SET_LEDS: u8 = 0xed;
+const RESPONSE_ACK: u8 = 0xfa;
+
+const LED_SCROLL_LOCK: u8 = 1 << 0;
+const LED_NUM_LOCK: u8 = 1 << 1;
+const LED_CAPS_LOCK: u8 = 1 << 2;
+
+const KEYBOARD_IRQ: u8 = 1;
+
+// Scrive un byte per la tastiera, aspettando che il controller sia pronto.
+fn write_data(byte: u8) {
+	unsafe {
+		while inb(STATUS_PORT) & STATUS_INPUT_FULL != 0 {}
+		outb(DATA_PORT, byte);
+	}
+}
+
+// ---
+
+// KEY EVENTS.
+
+bitflags! {
+	pub flags Modifiers: u16 {
+		const LEFT_SHIFT = 1 << 0,
+		const RIGHT_SHIFT = 1 << 1,
+		const LEFT_CTRL = 1 << 2,
+		const RIGHT_CTRL = 1 << 3,
+		const LEFT_ALT = 1 << 4,
+		const RIGHT_ALT = 1 << 5,
+		const CAPS_LOCK = 1 << 6,
+		const NUM_LOCK = 1 << 7,
+		const SCROLL_LOCK = 1 << 8,
+	}
+}
+
+impl Modifiers {
+	pub fn shift(&self) -> bool {
+		self.intersects(LEFT_SHIFT | RIGHT_SHIFT)
+	}
+
+	pub fn ctrl(&self) -> bool {
+		self.intersects(LEFT_CTRL | RIGHT_CTRL)
+	}
+
+	pub fn alt(&self) -> bool {
+		self.intersects(LEFT_ALT | RIGHT_ALT)
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct KeyEvent {
+	pub code: KeyCode,
+	pub pressed: bool,
+	// Modificatori attivi dopo l'evento.
+	pub modifiers: Modifiers,
+	// Carattere prodotto, solo per le pressioni.
+	pub character: Option<char>,
+}
+
+// ---
+
+// RING BUFFER.
+// Un solo produttore (l'handler della IRQ 1) e un solo consumatore:
+// bastano due indici atomici, senza lock. Se il buffer è pieno i nuovi
+// eventi vengono scartati.
+
+const RING_SIZE: usize = 128;
+
+struct EventRing {
+	events: UnsafeCell<[Option<KeyEvent>; RING_SIZE]>,
+	// Indici liberi di crescere: la posizione è l'indice modulo RING_SIZE.
+	head: AtomicUsize,
+	tail: AtomicUsize,
+	dropped: AtomicUsize,
+}
+
+unsafe impl Sync for EventRing {}
+
+impl EventRing {
+	const fn new() -> EventRing {
+		EventRing {
+			events: UnsafeCell::new([None; RING_SIZE]),
+			head: AtomicUsize::new(0),
+			tail: AtomicUsize::new(0),
+			dropped: AtomicUsize::new(0),
+		}
+	}
+
+	fn push(&self, event: KeyEvent) {
+		let head = self.head.load(Ordering::Relaxed);
+		let tail = self.tail.load(Ordering::Acquire);
+		if head.wrapping_sub(tail) == RING_SIZE {
+			self.dropped.fetch_add(1, Ordering::Relaxed);
+			return;
+		}
+
+		unsafe { (*self.events.get())[head % RING_SIZE] = Some(event) };
+		self.head.store(head.wrapping_add(1), Ordering::Release);
+	}
+
+	fn pop(&self) -> Option<KeyEvent> {
+		let tail = self.tail.load(Ordering::Relaxed);
+		let head = self.head.load(Ordering::Acquire);
+		if tail == head {
+			return None;
+		}
+
+		let event = unsafe { (*self.events.get())[tail % RING_SIZE].take() };
+		self.tail.store(tail.wrapping_add(1), Ordering::Release);
+		event
+	}
+}
+
+static EVENTS: EventRing = EventRing::new();
+
+// ---
+
+// DECODER.
+
+struct Decoder {
+	extended: bool,
+	// Byte della sequenza di Pausa ancora da scartare.
+	pause_bytes: u8,
+	modifiers: Modifiers,
+	// Tasti lock tenuti premuti, per ignorare l'autorepeat.
+	locks_down: Modifiers,
+	// LED da inviare quando la tastiera conferma COMMAND_SET_LEDS.
+	pending_leds: Option<u8>,
+	layout: &'static KeyboardLayout,
+	// Accento del tasto morto premuto per ultimo.
+	dead_key: Option<char>,
+}
+
+// Usato solo dall'handler della IRQ, con gli interrupt disabilitati.
+static DECODER: Mutex<Decoder> = Mutex::new(Decoder {
+	extended: false,
+	pause_bytes: 0,
+	modifiers: Modifiers { bits: 0 },
+	locks_down: Modifiers { bits: 0 },
+	pending_leds: None,
+	layout: &layout::US,
+	dead_key: None,
+});
+
+impl Decoder {
+	fn process(&mut self, byte: u8) -> Option<KeyEvent> {
+		if byte == RESPONSE_ACK {
+			if let Some(leds) = self.pending_leds.take() {
+				write_data(leds);
+			}
+			return None;
+		}
+
+		// La sequenza di Pausa contiene i codici di Ctrl e NumLock: va
+		// consumata tutta, poi diventa una sola pressione di Pause.
+		if self.pause_bytes > 0 {
+			self.pause_bytes -= 1;
+			if self.pause_bytes > 0 {
+				return None;
+			}
+			return Some(KeyEvent {
+				code: KeyCode::Pause,
+				pressed: true,
+				modifiers: self.modifiers,
+				character: None,
+			});
+		}
+
+		if byte == scancode::PAUSE_PREFIX {
+			self.pause_bytes = scancode::PAUSE_SEQUENCE_LENGTH;
+			return None;
+		}
+
+		if byte == scancode::EXTENDED_PREFIX {
+			self.extended = true;
+			return None;
+		}
+
+		let extended = self.extended;
+		self.extended = false;
+		let (code, pressed) = match scancode::decode(byte, extended) {
+			Some(key) => key,
+			None => return None,
+		};
+
+		self.update_modifiers(code, pressed);
+
+		let character = if pressed { self.character(code) } else { None };
+		Some(KeyEvent {
+			code: code,
+			pressed: pressed,
+			modifiers: self.modifiers,
+			character: character,
+		})
+	}
+
+	// Ctrl e Alt sinistro danno combinazioni, non caratteri. AltGr (Alt
+	// destro) è gestito dal layout.
+	fn character(&mut self, code: KeyCode) -> Option<char> {
+		if self.modifiers.ctrl() || self.modifiers.contains(LEFT_ALT) {
+			return None;
+		}
+
+		match self.layout.map_key(code, self.modifiers) {
+			KeyOutput::Nothing => None,
+			KeyOutput::DeadKey(accent) => {
+				// Due volte lo stesso tasto morto danno l'accento.
+				if self.dead_key == Some(accent) {
+					self.dead_key = None;
+					return Some(accent);
+				}
+				self.dead_key = Some(accent);
+				None
+			}
+			KeyOutput::Char(character) => match self.dead_key.take() {
+				Some(accent) => Some(layout::compose(accent, character)),
+				None => Some(character),
+			},
+		}
+	}
+
+	fn update_modifiers(&mut self, code: KeyCode, pressed: bool) {
+		let modifier = match code {
+			KeyCode::LeftShift => LEFT_SHIFT,
+			KeyCode::RightShift => RIGHT_SHIFT,
+			KeyCode::LeftCtrl => LEFT_CTRL,
+			KeyCode::RightCtrl => RIGHT_CTRL,
+			KeyCode::LeftAlt => LEFT_ALT,
+			KeyCode::RightAlt => RIGHT_ALT,
+			KeyCode::CapsLock => return self.update_lock(CAPS_LOCK, pressed),
+			KeyCode::NumLock => return self.update_lock(NUM_LOCK, pressed),
+			KeyCode::ScrollLock => return self.update_lock(SCROLL_LOCK, pressed),
+			_ => return,
+		};
+
+		if pressed {
+			self.modifiers.insert(modifier);
+		}
+		else {
+			self.modifiers.remove(modifier);
+		}
+	}
+
+	// I lock cambiano alla pressione. L'autorepeat manda altre pressioni
+	// senza rilasci: contano solo se il tasto era stato rilasciato.
+	fn update_lock(&mut self, lock: Modifiers, pressed: bool) {
+		if !pressed {
+			self.locks_down.remove(lock);
+			return;
+		}
+		if self.locks_down.contains(lock) {
+			return;
+		}
+
+		self.locks_down.insert(lock);
+		self.modifiers.toggle(lock);
+
+		let mut leds = 0;
+		if self.modifiers.contains(SCROLL_LOCK) { leds |= LED_SCROLL_LOCK; }
+		if self.modifiers.contains(NUM_LOCK) { leds |= LED_NUM_LOCK; }
+		if self.modifiers.contains(CAPS_LOCK) { leds |= LED_CAPS_LOCK; }
+
+		self.pending_leds = Some(leds);
+		write_data(COMMAND_SET_LEDS);
+	}
+}
+
+// ---
+
+// DRIVER.
+
+static ECHO: AtomicBool = AtomicBool::new(true);
+
+pub fn init() {
+	// Scarta i byte rimasti nel controller dal BIOS.
+	unsafe {
+		while inb(STATUS_PORT) & STATUS_OUTPUT_FULL != 0 {
+			inb(DATA_PORT);
+		}
+	}
+
+	irq::register(KEYBOARD_IRQ, keyboard_handler);
+}
+
+fn keyboard_handler(_irq: u8) {
+	let byte = unsafe { inb(DATA_PORT) };
+
+	let event = match DECODER.lock().process(byte) {
+		Some(event) => event,
+		None => return,
+	};
+
+	// Shift+PgUp/PgDn scorrono la console, ovunque ci si trovi.
+	if event.pressed && event.modifiers.shift() {
+		match event.code {
+			KeyCode::PageUp => vga_buffer::scroll_page_up(),
+			KeyCode::PageDown => vga_buffer::scroll_page_down(),
+			_ => {}
+		}
+	}
+
+	if ECHO.load(Ordering::Relaxed) {
+		match event.character {
+			Some(character) if character == '\n' || !character.is_control() => {
+				print!("{}", character);
+			}
+			_ => {}
+		}
+	}
+
+	EVENTS.push(event);
+}
+
+pub fn set_layout(layout: &'static KeyboardLayout) {
+	interrupts::without_interrupts(|| {
+		let mut decoder = DECODER.lock();
+		decoder.layout = layout;
+		decoder.dead_key = None;
+	});
+}
+
+pub fn layout() -> &'static KeyboardLayout {
+	interrupts::without_interrupts(|| DECODER.lock().layout)
+}
+
+// Prossimo evento, se c'è. Un solo consumatore alla volta.
+pub fn read_event() -> Option<KeyEvent> {
+	EVENTS.pop()
+}
+
+// Eventi persi perché il buffer era pieno.
+pub fn dropped_events() -> usize {
+	EVENTS.dropped.load(Ordering::Relaxed)
+}
+
+// Stampa i caratteri digitati direttamente dall'handler.
+pub fn set_echo(enabled: bool) {
+	ECHO.store(enabled, Ordering::Relaxed);
+}
+
+// ---
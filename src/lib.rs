@@ -1,17 +1,38 @@
 #![feature(lang_items)]
-#![feature(const_fn, unique)]
+#![feature(asm)]
+#![feature(alloc, alloc_error_handler)]
+#![feature(const_fn, panic_info_message)]
 #![no_std]
 
 extern crate rlibc;
+extern crate alloc;
 extern crate volatile;
 extern crate spin;
 extern crate multiboot2;
+extern crate x86;
 #[macro_use]
 extern crate bitflags;
 
+use core::fmt;
+use core::panic::PanicInfo;
+
 #[macro_use]
 mod vga_buffer;
+#[macro_use]
+mod serial;
 mod memory;
+mod cpu;
+mod acpi;
+mod interrupts;
+mod timer;
+mod clock;
+mod rtc;
+mod keyboard;
+mod cmdline;
+mod shell;
+
+#[global_allocator]
+static HEAP_ALLOCATOR: memory::LockedHeap = memory::LockedHeap::empty();
 
 // PAGE FLAGS.
 
@@ -20,13 +41,14 @@ mod memory;
 #[no_mangle]
 pub extern fn rust_main(multiboot_info_pointer: usize) 
 {
-	//ATTENZIONE: stack piccolo e nessuna pagina di controllo
-
 	let system_name = "DegradOS";
 	let bit_mode = 64;
 	
 	vga_buffer::clear_screen();	
 	
+	// COM1 per primo: da qui in poi log! arriva anche sulla seriale.
+	serial::init();
+	
 	//print_DegradOS();
 	blubbering(system_name, bit_mode);
 	
@@ -34,12 +56,55 @@ pub extern fn rust_main(multiboot_info_pointer: usize)
 	print_info(multiboot_info_pointer, boot_info);
 	
 	// Test frame allocation.
+	// Usa un allocatore separato sulla tabella delle pagine di boot:
+	// va eseguito prima del remapping del kernel.
 	frame_allocation_test(multiboot_info_pointer, boot_info);
 	
-	vga_buffer::print_centered(system_name);
+	// Eccezioni della CPU: da qui in poi niente triple fault silenziosi.
+	interrupts::init();
 	
+	// TSC calibrato contro il PIT, a interrupt ancora disabilitati.
+	clock::init();
 	
-	loop{}
+	// NXE e WP devono essere attivi prima del remapping, che usa NO_EXECUTE
+	// e pagine in sola lettura.
+	cpu::init();
+	
+	// Remapping del kernel con stack guard page.
+	memory::init(boot_info);
+	
+	// Con l'heap pronto le righe che escono dallo schermo restano visibili.
+	vga_buffer::enable_scrollback();
+	print_frame_info(&mut vga_buffer::Screen).unwrap();
+	
+	// Test heap.
+	heap_allocation_test();
+	
+	// Le cache si riempiono solo con le prime allocazioni.
+	print_slab_info(&mut vga_buffer::Screen).unwrap();
+	
+	breakpoint_test();
+	
+	// Local APIC e I/O APIC se presenti (QEMU: -machine q35), altrimenti PIC.
+	interrupts::init_apic();
+	
+	// Tick di sistema sulla IRQ 0.
+	timer::init(timer::DEFAULT_FREQUENCY);
+	
+	// Tastiera PS/2 sulla IRQ 1, con eco dei caratteri.
+	keyboard::init();
+	select_keyboard_layout(multiboot_info_pointer);
+	
+	// Ricezione dalla seriale via IRQ 4.
+	serial::enable_receive_interrupt();
+	
+	// Da qui in poi le IRQ registrate dai driver vengono servite.
+	interrupts::enable();
+	
+	vga_buffer::print_centered(system_name);
+	
+	// La shell non ritorna: fra un tasto e l'altro la CPU dorme.
+	shell::run(multiboot_info_pointer);
 }
 
 pub fn blubbering(system_name: &str, bit_mode: u8) {
@@ -49,38 +114,108 @@ pub fn blubbering(system_name: &str, bit_mode: u8) {
 	println!("Cool!!");
 	println!("");
 	println!("Call me Neo.");
+	println!("");
+	println!("It's {}. Wake up, Neo...", rtc::now());
+}
+
+// Layout scelto con `keymap=<nome>` sulla riga di comando, US se manca.
+pub fn select_keyboard_layout(multiboot_info_pointer: usize) {
+	let command_line = cmdline::command_line(multiboot_info_pointer);
+	if let Some(name) = cmdline::option(command_line, "keymap") {
+		match keyboard::layout::by_name(name) {
+			Some(layout) => keyboard::set_layout(layout),
+			None => println!("Unknown keymap '{}'.", name),
+		}
+	}
+	
+	log!("Keyboard layout: {}", keyboard::layout().name());
 }
 
 pub fn print_info(	multiboot_info_pointer: usize,
 					boot_info: &multiboot2::BootInformation) 
 {
-	print_multiboot_info(boot_info);
-	//print_kernel_sections(boot_info);
+	print_multiboot_info(&mut vga_buffer::Screen, boot_info).unwrap();
+	//print_kernel_sections(&mut vga_buffer::Screen, boot_info).unwrap();
 	print_kernel_start_end(boot_info);
 	print_multiboot_start_end(multiboot_info_pointer, boot_info);
 }
 
-pub fn print_multiboot_info(boot_info: &multiboot2::BootInformation) {
+// Le funzioni print_*_info scrivono su `out`: vga_buffer::Screen al boot,
+// ShellOutput dalla shell. I contatori vengono copiati prima di stampare,
+// così nessun lock resta preso mentre si scrive.
+
+pub fn print_multiboot_info<W>(out: &mut W, boot_info: &multiboot2::BootInformation)
+	-> fmt::Result
+	where W: fmt::Write
+{
 	let memory_map_tag = boot_info.memory_map_tag().expect("Memory tag required");
 	
-	println!("---");
-	println! ("Memory areas (Aaaah!! I'm naked!!):");
+	try!(writeln!(out, "---"));
+	try!(writeln!(out, "Memory areas (Aaaah!! I'm naked!!):"));
 	for area in memory_map_tag.memory_areas() {
-		println!(	"    Start: 0x{:x}, Length: 0x{:x}",
-					area.base_addr, 
-					area.length);
+		try!(writeln!(out, "    Start: 0x{:x}, Length: 0x{:x}",
+					  area.base_addr, 
+					  area.length));
 	}
+	Ok(())
 }
 
-pub fn print_kernel_sections(boot_info: &multiboot2::BootInformation) {	
+pub fn print_frame_info<W: fmt::Write>(out: &mut W) -> fmt::Result {
+	let (free_frames, total_frames) = memory::with_memory_controller(|controller| {
+		let frame_allocator = controller.frame_allocator();
+		(frame_allocator.free_frames(), frame_allocator.total_frames())
+	});
+	
+	try!(writeln!(out, "---"));
+	writeln!(out, "Free frames: {} of {}", free_frames, total_frames)
+}
+
+pub fn print_heap_info<W: fmt::Write>(out: &mut W) -> fmt::Result {
+	let (used_bytes, mapped_bytes) = {
+		let heap = HEAP_ALLOCATOR.lock();
+		(heap.used_bytes(), heap.mapped_bytes())
+	};
+	
+	writeln!(out, "Heap: {} bytes used, {} bytes mapped", used_bytes, mapped_bytes)
+}
+
+pub fn print_slab_info<W: fmt::Write>(out: &mut W) -> fmt::Result {
+	// Dimensione, oggetti usati, oggetti liberi e pagine di ogni cache.
+	let mut counters = [(0, 0, 0, 0); memory::CACHE_COUNT];
+	{
+		let slab_allocator = memory::SLAB_ALLOCATOR.lock();
+		for (counter, cache) in counters.iter_mut().zip(slab_allocator.caches()) {
+			*counter = (cache.object_size(),
+						cache.used_objects(),
+						cache.free_objects(),
+						cache.pages());
+		}
+	}
+	
+	try!(writeln!(out, "Slab caches:"));
+	for &(object_size, used, free, pages) in counters.iter() {
+		if pages == 0 {
+			continue;
+		}
+		try!(writeln!(out, "    {:>4} B: {} used, {} free, {} pages",
+					  object_size, used, free, pages));
+	}
+	Ok(())
+}
+
+pub fn print_kernel_sections<W>(out: &mut W, boot_info: &multiboot2::BootInformation)
+	-> fmt::Result
+	where W: fmt::Write
+{	
 	let elf_sections_tag = boot_info.elf_sections_tag()
     .expect("Elf-sections tag required");
-	println!("---");
-	println!("Kernel sections:");
+	try!(writeln!(out, "---"));
+	try!(writeln!(out, "Kernel sections:"));
 	for section in elf_sections_tag.sections() {
-		println!("    addr: 0x{:x}, size: 0x{:x}, flags: 0x{:x}",
-			section.addr, section.size, section.flags);
+		try!(writeln!(out, "    addr: 0x{:x}, size: 0x{:x}, flags: 0x{:x}",
+			section.addr, section.size, section.flags));
 	}
+	Ok(())
 }
 
 pub fn print_kernel_start_end(boot_info: &multiboot2::BootInformation) {
@@ -108,6 +243,10 @@ pub fn print_multiboot_start_end(multiboot_information_pointer: usize,
 	println! ("Multiboot end: {}", multiboot_end);
 }
 
+// Test di boot: girano a ogni avvio e stampano i risultati a schermo.
+// Le parti che usano l'interno dei moduli stanno nelle loro sezioni
+// TESTING (memory::test_paging, memory::test_over_aligned_allocation).
+
 pub fn frame_allocation_test(multiboot_information_pointer: usize,
 							 boot_info: &multiboot2::BootInformation) {
 	let memory_map_tag = boot_info.memory_map_tag().expect("Memory tag required");
@@ -145,24 +284,70 @@ pub fn frame_allocation_test(multiboot_information_pointer: usize,
     }
 }
 
+pub fn heap_allocation_test() {
+	use alloc::boxed::Box;
+	use alloc::vec::Vec;
+	use alloc::string::String;
+	
+	let heap_test = Box::new(42);
+	println!("---");
+	println!("Box: {}", heap_test);
+	
+	let mut vec_test: Vec<usize> = (0..500).collect();
+	vec_test.push(500);
+	println!("Vec: {} elements, sum {}", vec_test.len(), vec_test.iter().sum::<usize>());
+	
+	let mut string_test = String::from("Degrad");
+	string_test.push_str("OS");
+	println!("String: {}", string_test);
+	
+	memory::test_over_aligned_allocation();
+	
+	print_heap_info(&mut vga_buffer::Screen).unwrap();
+}
+
+pub fn breakpoint_test() {
+	// Il breakpoint handler stampa e riprende l'esecuzione.
+	unsafe { asm!("int3" :::: "volatile") };
+	println!("It did not crash!");
+}
+
 #[lang = "eh_personality"] extern fn eh_personality() {}
-#[lang =  "panic_fmt"] 
-extern fn panic_fmt(fmt: core::fmt::Arguments, file: &str, line: u32) -> !
+#[panic_handler]
+fn panic(info: &PanicInfo) -> !
 {
+	interrupts::disable();
+	
 	vga_buffer::clear_screen();
 	
+	let (file, line) = match info.location() {
+		Some(location) => (location.file(), location.line()),
+		None => ("<unknown>", 0),
+	};
 	println! ("=========================");
 	println! ("Nooooooo!! Kernel panic!!");
 	println! ("(Aha! If it had been blue, it would have worked)");
 	println! ("");
 	println! ("-------------------------");
-	println!("PANIC in {} at line {}:", file, line);
-    println!("    {}", fmt);
+	let timestamp = clock::Timestamp::now();
+	println!("PANIC at {} in {} at line {}:", timestamp, file, line);
+	serial_println!("PANIC at {} in {} at line {}:", timestamp, file, line);
+	if let Some(message) = info.message() {
+		println!("    {}", message);
+		serial_println!("    {}", message);
+	}
 	println! ("=========================");
 	
 	loop{}
 }
 
+#[alloc_error_handler]
+fn alloc_error(layout: core::alloc::Layout) -> ! {
+	println! ("");
+	println! ("Out of kernel heap: size {}, align {}", layout.size(), layout.align());
+	panic!("Heap allocation failed.");
+}
+
 // Fake function. Ricompileremo libcore with panic="abort".
 #[allow(non_snake_case)]
 #[no_mangle]
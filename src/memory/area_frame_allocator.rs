@@ -1,8 +1,21 @@
 use memory::{Frame, FrameAllocator};
 use multiboot2::{MemoryAreaIter, MemoryArea };
 
+// Memoria fisica mappata in identità dalle tabelle di boot (boot.asm):
+// 512 huge page da 2 MiB.
+const BOOT_IDENTITY_MAP_END: usize = 512 * 2 * 1024 * 1024;
+
+// Nel primo word di un frame della free list: nessun frame successivo.
+const FREE_LIST_END: usize = !0;
+
 pub struct AreaFrameAllocator {
 	next_free_frame: Frame,
+	// Frame liberati: ognuno contiene nel primo word il numero del
+	// successivo, quindi la lista non ha limiti di capacità.
+	free_list: Option<Frame>,
+	// I frame sono raggiungibili in identità finché sono attive le
+	// tabelle di boot.
+	identity_mapped: bool,
 	current_area: Option<&'static MemoryArea>,
 	areas: MemoryAreaIter,
 	kernel_start: Frame,
@@ -13,6 +26,13 @@ pub struct AreaFrameAllocator {
 
 impl FrameAllocator for AreaFrameAllocator {
 	fn allocate_frame(&mut self) -> Option<Frame> {
+		if let Some(frame) = self.free_list.take() {
+			let next = unsafe { *(frame.start_address() as *const usize) };
+			if next != FREE_LIST_END {
+				self.free_list = Some(Frame { number: next });
+			}
+			return Some(frame);
+		}
 		
 		if let Some(area) = self.current_area {
 			// Simulazione della clonazione del Frame
@@ -48,10 +68,22 @@ impl FrameAllocator for AreaFrameAllocator {
 		}	
 	}
 	
+	// Il frame entra in testa alla free list, scrivendo nel suo primo word
+	// il numero della vecchia testa. Se il frame non è raggiungibile in
+	// identità non si può scrivere nella lista: il frame va perso.
 	fn deallocate_frame(&mut self, frame: Frame) {
-		// Non avendo una struttura dati per allocare frame liberi
-		// lancio kernel panic con la macro unimplemented.
-		unimplemented!();
+		if !self.identity_mapped || frame.start_address() >= BOOT_IDENTITY_MAP_END {
+			log!("Boot allocator: {:?} not identity mapped, leaked.", frame);
+			return;
+		}
+		
+		let next = match self.free_list.take() {
+			Some(next) => next.number,
+			None => FREE_LIST_END,
+		};
+		unsafe { *(frame.start_address() as *mut usize) = next };
+		
+		self.free_list = Some(frame);
 	}
 }
 
@@ -76,6 +108,8 @@ impl AreaFrameAllocator {
 	{
 		let mut allocator = AreaFrameAllocator {
 			next_free_frame: Frame::containing_address(0),
+			free_list: None,
+			identity_mapped: true,
 			current_area: None,
 			areas: memory_areas,
 			kernel_start: Frame::containing_address(kernel_start),
@@ -87,4 +121,19 @@ impl AreaFrameAllocator {
 		allocator.choose_next_area();
 		allocator
 	}
+	
+	// Primo frame non ancora consegnato: i frame precedenti sono stati
+	// allocati oppure saltati.
+	pub fn next_free_frame(&self) -> Frame {
+		self.next_free_frame.clone()
+	}
+	
+	// Da chiamare dopo il passaggio alla tabella del kernel, che non mappa
+	// più la memoria fisica in identità: la free list non è più leggibile.
+	// I suoi frame stanno tutti prima di next_free_frame, quindi il
+	// KernelFrameAllocator li considera comunque occupati.
+	pub fn leave_identity_map(&mut self) {
+		self.identity_mapped = false;
+		self.free_list = None;
+	}
 }
@@ -0,0 +1,115 @@
+use core::slice;
+
+const BITS_PER_WORD: usize = 64;
+
+// BITMAP.
+// Sequenza di bit appoggiata su una zona di memoria già mappata.
+// Le ricerche compilate sono solo quelle del KernelFrameAllocator scelto.
+
+pub struct Bitmap {
+	words: &'static mut [u64],
+	len: usize,
+}
+
+impl Bitmap {
+	// Numero di parole da 64 bit necessarie per contenere `len` bit.
+	pub fn words_for(len: usize) -> usize {
+		(len + BITS_PER_WORD - 1) / BITS_PER_WORD
+	}
+
+	// La memoria da `address` deve essere mappata, scrivibile e
+	// contenere almeno `Bitmap::words_for(len)` parole.
+	pub unsafe fn new(address: usize, len: usize) -> Bitmap {
+		Bitmap {
+			words: slice::from_raw_parts_mut(address as *mut u64,
+											 Bitmap::words_for(len)),
+			len: len,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn get(&self, index: usize) -> bool {
+		assert! (index < self.len, "Bitmap index {} out of range.", index);
+		self.words[index / BITS_PER_WORD] & (1 << (index % BITS_PER_WORD)) != 0
+	}
+
+	pub fn set(&mut self, index: usize) {
+		assert! (index < self.len, "Bitmap index {} out of range.", index);
+		self.words[index / BITS_PER_WORD] |= 1 << (index % BITS_PER_WORD);
+	}
+
+	pub fn clear(&mut self, index: usize) {
+		assert! (index < self.len, "Bitmap index {} out of range.", index);
+		self.words[index / BITS_PER_WORD] &= !(1 << (index % BITS_PER_WORD));
+	}
+
+	pub fn set_all(&mut self) {
+		for word in self.words.iter_mut() {
+			*word = !0;
+		}
+	}
+
+	#[cfg(not(feature = "bitmap_frame_allocator"))]
+	pub fn clear_all(&mut self) {
+		for word in self.words.iter_mut() {
+			*word = 0;
+		}
+	}
+
+	// Primo bit a zero a partire dalla parola `start_word`, senza tornare
+	// indietro. Salta 64 bit alla volta le parole piene.
+	#[cfg(feature = "bitmap_frame_allocator")]
+	pub fn first_clear_from(&self, start_word: usize) -> Option<usize> {
+		for word_index in start_word..self.words.len() {
+			let word = self.words[word_index];
+			if word != !0 {
+				let index = word_index * BITS_PER_WORD +
+							(!word).trailing_zeros() as usize;
+				if index < self.len {
+					return Some(index);
+				}
+			}
+		}
+		None
+	}
+
+	// Primo bit a uno a partire dalla parola `start_word`.
+	#[cfg(not(feature = "bitmap_frame_allocator"))]
+	pub fn first_set_from(&self, start_word: usize) -> Option<usize> {
+		for word_index in start_word..self.words.len() {
+			let word = self.words[word_index];
+			if word != 0 {
+				let index = word_index * BITS_PER_WORD +
+							word.trailing_zeros() as usize;
+				if index < self.len {
+					return Some(index);
+				}
+			}
+		}
+		None
+	}
+
+	// Primo blocco di `count` bit consecutivi a zero, allineato a `align` bit.
+	#[cfg(feature = "bitmap_frame_allocator")]
+	pub fn find_clear_run(&self, count: usize, align: usize) -> Option<usize> {
+		let mut start = 0;
+		while start + count <= self.len {
+			match (start..start + count).rev().find(|&i| self.get(i)) {
+				// Riparte dopo il bit occupato, mantenendo l'allineamento.
+				Some(used) => start = (used / align + 1) * align,
+				None => return Some(start),
+			}
+		}
+		None
+	}
+
+	#[cfg(feature = "bitmap_frame_allocator")]
+	pub fn word_index(index: usize) -> usize {
+		index / BITS_PER_WORD
+	}
+}
+
+// ---
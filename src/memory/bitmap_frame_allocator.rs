@@ -0,0 +1,166 @@
+use memory::{PAGE_SIZE, Frame, FrameAllocator, BITMAP_START};
+use memory::AreaFrameAllocator;
+use memory::bitmap::Bitmap;
+use memory::paging::{ActivePageTable, Page, WRITEABLE, NO_EXECUTE};
+use multiboot2::MemoryAreaIter;
+
+// BITMAP FRAME ALLOCATOR.
+// Un bit per ogni frame della memoria fisica: 1 occupato, 0 libero.
+// La bitmap viene creata dopo il remapping del kernel, in frame presi
+// dall'allocatore di boot e mappati a partire da BITMAP_START.
+
+pub struct BitmapFrameAllocator {
+	bitmap: Bitmap,
+	// Parola della bitmap da cui partire con la prossima ricerca.
+	next_word: usize,
+	free_frames: usize,
+	total_frames: usize,
+}
+
+impl BitmapFrameAllocator {
+	// Tutti i frame prima del next_free_frame dell'allocatore di boot sono
+	// considerati occupati: sono stati consegnati, ad esempio per le tabelle
+	// delle pagine e per la bitmap stessa, o saltati perché del kernel.
+	// Anche quelli della sua free list restano occupati: se ne perdono al più pochi.
+	pub fn new(	kernel_start: usize, kernel_end: usize,
+				multiboot_start: usize, multiboot_end: usize,
+				memory_areas: MemoryAreaIter,
+				active_table: &mut ActivePageTable,
+				boot_allocator: &mut AreaFrameAllocator)
+		-> BitmapFrameAllocator
+	{
+		let frame_count = memory_areas.clone()
+			.map(|area| ((area.base_addr + area.length) as usize) / PAGE_SIZE)
+			.max().expect("No memory areas.");
+
+		// Mappa le pagine della bitmap.
+		let bitmap_bytes = Bitmap::words_for(frame_count) * 8;
+		let bitmap_pages = (bitmap_bytes + PAGE_SIZE - 1) / PAGE_SIZE;
+		let start_page = Page::containing_address(BITMAP_START);
+		let end_page = Page::containing_address(BITMAP_START + bitmap_pages * PAGE_SIZE - 1);
+		for page in Page::range_inclusive(start_page, end_page) {
+			active_table.map(page, WRITEABLE | NO_EXECUTE, boot_allocator);
+		}
+
+		let mut bitmap = unsafe { Bitmap::new(BITMAP_START, frame_count) };
+
+		// Tutto occupato, tranne i frame interamente contenuti in un'area.
+		bitmap.set_all();
+		for area in memory_areas {
+			let first = ((area.base_addr as usize) + PAGE_SIZE - 1) / PAGE_SIZE;
+			let last = ((area.base_addr + area.length) as usize) / PAGE_SIZE;
+			for number in first..last {
+				bitmap.clear(number);
+			}
+		}
+
+		let mut allocator = BitmapFrameAllocator {
+			bitmap: bitmap,
+			next_word: 0,
+			free_frames: 0,
+			total_frames: 0,
+		};
+
+		// Letto solo ora: la bitmap è già stata mappata.
+		let boot_allocator_end = boot_allocator.next_free_frame();
+		if boot_allocator_end.number > 0 {
+			allocator.mark_used(Frame::containing_address(0),
+								Frame { number: boot_allocator_end.number - 1 });
+		}
+		allocator.mark_used(Frame::containing_address(kernel_start),
+							Frame::containing_address(kernel_end - 1));
+		allocator.mark_used(Frame::containing_address(multiboot_start),
+							Frame::containing_address(multiboot_end - 1));
+
+		let free_frames = (0..frame_count).filter(|&n| !allocator.bitmap.get(n))
+										  .count();
+		allocator.free_frames = free_frames;
+		allocator.total_frames = free_frames;
+		allocator
+	}
+
+	fn mark_used(&mut self, start: Frame, end: Frame) {
+		for frame in Frame::range_inclusive(start, end) {
+			if frame.number < self.bitmap.len() {
+				self.bitmap.set(frame.number);
+			}
+		}
+	}
+
+	pub fn free_frames(&self) -> usize {
+		self.free_frames
+	}
+
+	// Frame disponibili subito dopo l'inizializzazione.
+	pub fn total_frames(&self) -> usize {
+		self.total_frames
+	}
+
+	// ALLOCAZIONE CONTIGUA.
+
+	// Alloca `count` frame fisicamente contigui, ad esempio per i buffer DMA,
+	// con il primo frame allineato ad `align` frame. Ritorna il primo frame.
+	pub fn allocate_contiguous(&mut self, count: usize, align: usize) -> Option<Frame> {
+		assert! (count > 0 && align > 0);
+
+		let start = match self.bitmap.find_clear_run(count, align) {
+			Some(start) => start,
+			None => return None,
+		};
+
+		for number in start..start + count {
+			self.bitmap.set(number);
+		}
+		self.free_frames -= count;
+
+		Some(Frame { number: start })
+	}
+
+	pub fn deallocate_contiguous(&mut self, frame: Frame, count: usize) {
+		for number in frame.number..frame.number + count {
+			self.deallocate_frame(Frame { number: number });
+		}
+	}
+
+	// Stessa interfaccia del BuddyAllocator: 2^order frame allineati.
+	pub fn allocate_frames(&mut self, order: usize) -> Option<Frame> {
+		self.allocate_contiguous(1 << order, 1 << order)
+	}
+
+	pub fn deallocate_frames(&mut self, frame: Frame, order: usize) {
+		self.deallocate_contiguous(frame, 1 << order);
+	}
+
+	// ---
+}
+
+impl FrameAllocator for BitmapFrameAllocator {
+	fn allocate_frame(&mut self) -> Option<Frame> {
+		// Le parole prima di next_word sono piene, quindi la ricerca
+		// riparte da dove si era fermata: O(1) ammortizzato.
+		let found = self.bitmap.first_clear_from(self.next_word)
+					.or_else(|| self.bitmap.first_clear_from(0));
+
+		found.map(|number| {
+			self.bitmap.set(number);
+			self.next_word = Bitmap::word_index(number);
+			self.free_frames -= 1;
+			Frame { number: number }
+		})
+	}
+
+	fn deallocate_frame(&mut self, frame: Frame) {
+		assert! (self.bitmap.get(frame.number),
+				 "Double free of {:?}.", frame);
+
+		self.bitmap.clear(frame.number);
+		self.free_frames += 1;
+
+		let word = Bitmap::word_index(frame.number);
+		if word < self.next_word {
+			self.next_word = word;
+		}
+	}
+}
+
+// ---
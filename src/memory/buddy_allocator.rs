@@ -0,0 +1,203 @@
+use memory::{PAGE_SIZE, Frame, FrameAllocator, BUDDY_START};
+use memory::AreaFrameAllocator;
+use memory::bitmap::Bitmap;
+use memory::paging::{ActivePageTable, Page, WRITEABLE, NO_EXECUTE};
+use multiboot2::MemoryAreaIter;
+
+// Ordine massimo: blocchi da 2^10 frame (4 MiB).
+// L'ordine 9 corrisponde a una huge page da 2 MiB.
+pub const MAX_ORDER: usize = 10;
+
+// BUDDY ALLOCATOR.
+// Per ogni ordine k una bitmap con un bit per blocco da 2^k frame:
+// 1 se il blocco è libero. Il blocco i di ordine k ha come buddy il
+// blocco i ^ 1, e i due insieme formano il blocco i / 2 di ordine k + 1.
+// Una bitmap in più ha un bit per frame, 1 se il frame è occupato: serve
+// a riconoscere i double free anche dentro blocchi già fusi o divisi.
+// Le bitmap stanno in frame presi dall'allocatore di boot, mappati
+// a partire da BUDDY_START.
+
+pub struct BuddyAllocator {
+	free_blocks: [Option<Bitmap>; MAX_ORDER + 1],
+	used_frames: Bitmap,
+	free_frames: usize,
+	total_frames: usize,
+}
+
+impl BuddyAllocator {
+	// Stesse esclusioni di AreaFrameAllocator::new: kernel e multiboot.
+	// Come per BitmapFrameAllocator, restano occupati anche i frame prima
+	// del next_free_frame dell'allocatore di boot.
+	pub fn new(	kernel_start: usize, kernel_end: usize,
+				multiboot_start: usize, multiboot_end: usize,
+				memory_areas: MemoryAreaIter,
+				active_table: &mut ActivePageTable,
+				boot_allocator: &mut AreaFrameAllocator)
+		-> BuddyAllocator
+	{
+		let frame_count = memory_areas.clone()
+			.map(|area| ((area.base_addr + area.length) as usize) / PAGE_SIZE)
+			.max().expect("No memory areas.");
+
+		// Le bitmap di tutti gli ordini e quella dei frame, una dopo l'altra.
+		let mut bitmap_words = Bitmap::words_for(frame_count);
+		for order in 0..MAX_ORDER + 1 {
+			bitmap_words += Bitmap::words_for(BuddyAllocator::blocks(frame_count, order));
+		}
+
+		let bitmap_pages = (bitmap_words * 8 + PAGE_SIZE - 1) / PAGE_SIZE;
+		let start_page = Page::containing_address(BUDDY_START);
+		let end_page = Page::containing_address(BUDDY_START + bitmap_pages * PAGE_SIZE - 1);
+		for page in Page::range_inclusive(start_page, end_page) {
+			active_table.map(page, WRITEABLE | NO_EXECUTE, boot_allocator);
+		}
+
+		// Tutti i frame occupati e nessun blocco libero, finché i frame
+		// delle aree non vengono restituiti.
+		let mut used_frames = unsafe { Bitmap::new(BUDDY_START, frame_count) };
+		used_frames.set_all();
+
+		let mut allocator = BuddyAllocator {
+			free_blocks: [None, None, None, None, None, None,
+						  None, None, None, None, None],
+			used_frames: used_frames,
+			free_frames: 0,
+			total_frames: 0,
+		};
+
+		let mut address = BUDDY_START + Bitmap::words_for(frame_count) * 8;
+		for order in 0..MAX_ORDER + 1 {
+			let blocks = BuddyAllocator::blocks(frame_count, order);
+			let mut bitmap = unsafe { Bitmap::new(address, blocks) };
+			bitmap.clear_all();
+			address += Bitmap::words_for(blocks) * 8;
+			allocator.free_blocks[order] = Some(bitmap);
+		}
+
+		// Letto solo ora: le bitmap sono già state mappate.
+		let boot_allocator_end = boot_allocator.next_free_frame();
+
+		let reserved = |number: usize| {
+			let address = number * PAGE_SIZE;
+			number < boot_allocator_end.number ||
+				(address >= kernel_start & !(PAGE_SIZE - 1) && address < kernel_end) ||
+				(address >= multiboot_start & !(PAGE_SIZE - 1) && address < multiboot_end)
+		};
+
+		// Ogni frame libero viene restituito singolarmente: i buddy liberi
+		// si fondono da soli negli ordini superiori.
+		for area in memory_areas {
+			let first = ((area.base_addr as usize) + PAGE_SIZE - 1) / PAGE_SIZE;
+			let last = ((area.base_addr + area.length) as usize) / PAGE_SIZE;
+			for number in first..last {
+				if !reserved(number) {
+					allocator.deallocate_frames(Frame { number: number }, 0);
+				}
+			}
+		}
+
+		allocator.total_frames = allocator.free_frames;
+		allocator
+	}
+
+	// Numero di blocchi interi di ordine `order` in `frame_count` frame.
+	fn blocks(frame_count: usize, order: usize) -> usize {
+		frame_count >> order
+	}
+
+	fn bitmap(&mut self, order: usize) -> &mut Bitmap {
+		self.free_blocks[order].as_mut().unwrap()
+	}
+
+	pub fn free_frames(&self) -> usize {
+		self.free_frames
+	}
+
+	// Frame disponibili subito dopo l'inizializzazione.
+	pub fn total_frames(&self) -> usize {
+		self.total_frames
+	}
+
+	// ALLOCAZIONE PER ORDINE.
+
+	// Alloca 2^order frame contigui, allineati a 2^order frame.
+	pub fn allocate_frames(&mut self, order: usize) -> Option<Frame> {
+		assert! (order <= MAX_ORDER, "Order {} too big.", order);
+
+		self.allocate_block(order).map(|block| {
+			let first = block << order;
+			for number in first..first + (1 << order) {
+				self.used_frames.set(number);
+			}
+			self.free_frames -= 1 << order;
+			Frame { number: first }
+		})
+	}
+
+	fn allocate_block(&mut self, order: usize) -> Option<usize> {
+		if let Some(block) = self.bitmap(order).first_set_from(0) {
+			self.bitmap(order).clear(block);
+			return Some(block);
+		}
+
+		if order == MAX_ORDER {
+			return None;
+		}
+
+		// Divide un blocco dell'ordine superiore e libera la seconda metà.
+		self.allocate_block(order + 1).map(|parent| {
+			let block = parent * 2;
+			self.bitmap(order).set(block + 1);
+			block
+		})
+	}
+
+	// Libera i 2^order frame a partire da `frame`, che deve essere allineato.
+	// Tutti i frame del blocco devono essere occupati: un frame già libero,
+	// da solo o dentro un blocco più grande, è un double free.
+	pub fn deallocate_frames(&mut self, frame: Frame, order: usize) {
+		assert! (order <= MAX_ORDER, "Order {} too big.", order);
+		assert! (frame.number % (1 << order) == 0,
+				 "{:?} is not aligned to order {}.", frame, order);
+		assert! (frame.number + (1 << order) <= self.used_frames.len(),
+				 "{:?} is not in physical memory.", frame);
+
+		for number in frame.number..frame.number + (1 << order) {
+			assert! (self.used_frames.get(number), "Double free of {:?}.", frame);
+			self.used_frames.clear(number);
+		}
+
+		self.free_frames += 1 << order;
+
+		let mut block = frame.number >> order;
+		let mut order = order;
+
+		// Fonde il blocco con il buddy finché anche il buddy è libero.
+		while order < MAX_ORDER {
+			let buddy = block ^ 1;
+			if buddy >= self.bitmap(order).len() || !self.bitmap(order).get(buddy) {
+				break;
+			}
+
+			self.bitmap(order).clear(buddy);
+			block /= 2;
+			order += 1;
+		}
+
+		self.bitmap(order).set(block);
+	}
+
+	// ---
+}
+
+impl FrameAllocator for BuddyAllocator {
+	fn allocate_frame(&mut self) -> Option<Frame> {
+		self.allocate_frames(0)
+	}
+
+	fn deallocate_frame(&mut self, frame: Frame) {
+		self.deallocate_frames(frame, 0)
+	}
+}
+
+// ---
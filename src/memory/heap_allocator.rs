@@ -0,0 +1,237 @@
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+use spin::Mutex;
+use memory::{self, PAGE_SIZE};
+use memory::slab_allocator::{self, SLAB_ALLOCATOR};
+
+// Pagine mappate come minimo ogni volta che l'heap cresce.
+const HEAP_GROW_PAGES: usize = 16;
+
+// LIST NODE.
+// Ogni zona libera dell'heap inizia con un nodo che ne contiene la
+// dimensione e punta alla zona libera successiva (ordinate per indirizzo).
+
+struct ListNode {
+	size: usize,
+	next: *mut ListNode,
+}
+
+// Dimensione e allineamento di ListNode.
+const MIN_BLOCK_SIZE: usize = 16;
+const BLOCK_ALIGN: usize = 8;
+
+// ---
+
+// HEAP.
+// Allocatore a lista concatenata first-fit. Quando nessuna zona libera
+// basta, mappa nuove pagine in fondo all'heap con memory::grow_heap.
+
+pub struct Heap {
+	head: *mut ListNode,
+	mapped_bytes: usize,
+	used_bytes: usize,
+}
+
+// I nodi stanno nelle pagine dell'heap, protette dal Mutex di LockedHeap.
+unsafe impl Send for Heap {}
+
+impl Heap {
+	pub const fn empty() -> Heap {
+		Heap {
+			head: 0 as *mut ListNode,
+			mapped_bytes: 0,
+			used_bytes: 0,
+		}
+	}
+
+	pub fn mapped_bytes(&self) -> usize {
+		self.mapped_bytes
+	}
+
+	pub fn used_bytes(&self) -> usize {
+		self.used_bytes
+	}
+
+	// Dimensione e allineamento effettivi di un blocco: devono poter
+	// contenere un ListNode quando il blocco verrà liberato.
+	fn block_layout(layout: &Layout) -> (usize, usize) {
+		let size = align_up(layout.size().max(MIN_BLOCK_SIZE), BLOCK_ALIGN);
+		let align = layout.align().max(BLOCK_ALIGN);
+		(size, align)
+	}
+
+	pub fn allocate(&mut self, layout: Layout) -> *mut u8 {
+		let (size, align) = Heap::block_layout(&layout);
+
+		loop {
+			if let Some(address) = unsafe { self.allocate_first_fit(size, align) } {
+				self.used_bytes += size;
+				return address as *mut u8;
+			}
+
+			// Nessuna zona abbastanza grande: l'heap cresce e si riprova.
+			let pages = ((size + align) / PAGE_SIZE + 1).max(HEAP_GROW_PAGES);
+			match memory::grow_heap(pages) {
+				Some((start, bytes)) => unsafe {
+					self.mapped_bytes += bytes;
+					self.add_free_region(start, bytes);
+				},
+				None => return ptr::null_mut(),
+			}
+		}
+	}
+
+	pub fn deallocate(&mut self, address: *mut u8, layout: Layout) {
+		let (size, _) = Heap::block_layout(&layout);
+		self.used_bytes -= size;
+		unsafe { self.add_free_region(address as usize, size) };
+	}
+
+	unsafe fn allocate_first_fit(&mut self, size: usize, align: usize) -> Option<usize> {
+		let mut previous: *mut ListNode = ptr::null_mut();
+		let mut current = self.head;
+
+		while !current.is_null() {
+			let region_start = current as usize;
+			let region_end = region_start + (*current).size;
+
+			// Gli avanzi davanti e dietro devono poter ospitare un nodo:
+			// se davanti resta troppo poco si passa all'indirizzo allineato
+			// successivo.
+			let mut alloc_start = align_up(region_start, align);
+			if alloc_start > region_start && alloc_start - region_start < MIN_BLOCK_SIZE {
+				alloc_start = align_up(region_start + MIN_BLOCK_SIZE, align);
+			}
+			let alloc_end = alloc_start + size;
+
+			let front = alloc_start - region_start;
+			let back = region_end.saturating_sub(alloc_end);
+
+			if alloc_end <= region_end && (back == 0 || back >= MIN_BLOCK_SIZE) {
+				let next = (*current).next;
+				if previous.is_null() {
+					self.head = next;
+				}
+				else {
+					(*previous).next = next;
+				}
+
+				if front > 0 {
+					self.add_free_region(region_start, front);
+				}
+				if back > 0 {
+					self.add_free_region(alloc_end, back);
+				}
+
+				return Some(alloc_start);
+			}
+
+			previous = current;
+			current = (*current).next;
+		}
+
+		None
+	}
+
+	// Inserisce la zona nella lista mantenendo l'ordine per indirizzo
+	// e la fonde con le zone adiacenti.
+	unsafe fn add_free_region(&mut self, address: usize, size: usize) {
+		assert_eq! (align_up(address, BLOCK_ALIGN), address);
+		assert! (size >= MIN_BLOCK_SIZE);
+
+		let mut previous: *mut ListNode = ptr::null_mut();
+		let mut current = self.head;
+		while !current.is_null() && (current as usize) < address {
+			previous = current;
+			current = (*current).next;
+		}
+
+		let node = address as *mut ListNode;
+		ptr::write(node, ListNode { size: size, next: current });
+		if previous.is_null() {
+			self.head = node;
+		}
+		else {
+			(*previous).next = node;
+		}
+
+		if !current.is_null() && address + size == current as usize {
+			(*node).size += (*current).size;
+			(*node).next = (*current).next;
+		}
+
+		if !previous.is_null() && previous as usize + (*previous).size == address {
+			(*previous).size += (*node).size;
+			(*previous).next = (*node).next;
+		}
+	}
+}
+
+// ---
+
+// LOCKED HEAP.
+
+pub struct LockedHeap(Mutex<Heap>);
+
+impl LockedHeap {
+	pub const fn empty() -> LockedHeap {
+		LockedHeap(Mutex::new(Heap::empty()))
+	}
+
+	pub fn lock(&self) -> ::spin::MutexGuard<Heap> {
+		self.0.lock()
+	}
+}
+
+// Gli oggetti fino a 2 KiB vanno alle cache dello slab allocator,
+// quelli più grandi alla lista dell'heap.
+unsafe impl GlobalAlloc for LockedHeap {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		match slab_allocator::size_class(&layout) {
+			Some(class) => SLAB_ALLOCATOR.lock().allocate(class),
+			None => self.0.lock().allocate(layout),
+		}
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		match slab_allocator::size_class(&layout) {
+			Some(class) => SLAB_ALLOCATOR.lock().deallocate(ptr, class),
+			None => self.0.lock().deallocate(ptr, layout),
+		}
+	}
+}
+
+// ---
+
+// TESTING.
+
+// Allocazione con allineamento 16 da una zona che inizia a 8 mod 16:
+// l'avanzo davanti sarebbe troppo piccolo per un nodo.
+pub fn test_over_aligned_allocation() {
+	static mut REGION: [u64; 32] = [0; 32];
+
+	let mut heap = Heap::empty();
+	let base = unsafe { &mut REGION as *mut _ as usize };
+	let start = if base % 16 == 0 { base + 8 } else { base };
+	let size = 128;
+	unsafe { heap.add_free_region(start, size) };
+
+	let layout = Layout::from_size_align(32, 16).unwrap();
+	let address = heap.allocate(layout.clone()) as usize;
+	assert! (address != 0, "Over-aligned allocation failed.");
+	assert_eq! (address % 16, 0);
+	assert! (address >= start + MIN_BLOCK_SIZE && address + 32 <= start + size);
+
+	heap.deallocate(address as *mut u8, layout);
+	assert_eq! (heap.used_bytes(), 0);
+	assert_eq! (unsafe { (*heap.head).size }, size);
+
+	println!("Over-aligned allocation at 0x{:x}: ok", address);
+}
+
+// ---
+
+fn align_up(address: usize, align: usize) -> usize {
+	(address + align - 1) & !(align - 1)
+}
+
@@ -1,12 +1,62 @@
 pub use self::area_frame_allocator::AreaFrameAllocator;
-use self::paging::PhysicalAddress;
-pub use self::paging::test_paging;
+#[cfg(feature = "bitmap_frame_allocator")]
+pub use self::bitmap_frame_allocator::BitmapFrameAllocator;
+#[cfg(not(feature = "bitmap_frame_allocator"))]
+pub use self::buddy_allocator::BuddyAllocator;
+pub use self::heap_allocator::{LockedHeap, test_over_aligned_allocation};
+pub use self::slab_allocator::{SLAB_ALLOCATOR, CACHE_COUNT};
+pub use self::paging::{PhysicalAddress, VirtualAddress, EntryFlags};
+pub use self::paging::{WRITEABLE, NO_CACHE, WRITE_THROUGH, NO_EXECUTE};
+use self::paging::{ActivePageTable, Page};
+pub use self::paging::{test_paging, PageWalk};
+
+use multiboot2::BootInformation;
+use spin::Mutex;
 
 mod area_frame_allocator;
+mod bitmap;
+#[cfg(feature = "bitmap_frame_allocator")]
+mod bitmap_frame_allocator;
+#[cfg(not(feature = "bitmap_frame_allocator"))]
+mod buddy_allocator;
+mod heap_allocator;
+mod slab_allocator;
 mod paging;
 
 pub const PAGE_SIZE: usize = 4096;
 
+// VIRTUAL LAYOUT.
+// Zone dello spazio virtuale riservate al kernel, fuori dalla identity map.
+
+// Bitmap del BitmapFrameAllocator (1 GiB).
+#[cfg(feature = "bitmap_frame_allocator")]
+pub const BITMAP_START: usize = 0o_000_001_000_000_0000;
+// Bitmap del BuddyAllocator (2 GiB).
+#[cfg(not(feature = "bitmap_frame_allocator"))]
+pub const BUDDY_START: usize = 0o_000_002_000_000_0000;
+// Heap del kernel (3 GiB), mappato su richiesta fino a HEAP_MAX_SIZE.
+pub const HEAP_START: usize = 0o_000_003_000_000_0000;
+pub const HEAP_MAX_SIZE: usize = 256 * 1024 * 1024;
+// Pagine delle cache dello slab allocator (4 GiB).
+pub const SLAB_START: usize = 0o_000_004_000_000_0000;
+pub const SLAB_MAX_SIZE: usize = 256 * 1024 * 1024;
+// Finestra sulla memoria fisica per MMIO e tabelle ACPI (5 GiB).
+pub const PHYSICAL_WINDOW_START: usize = 0o_000_005_000_000_0000;
+pub const PHYSICAL_WINDOW_SIZE: usize = 256 * 1024 * 1024;
+// ---
+
+// KERNEL FRAME ALLOCATOR.
+// Allocatore usato dal kernel dopo il boot, per i frame singoli e per i
+// blocchi contigui di 2^order frame (huge page, DMA). Di default è il
+// BuddyAllocator; con la feature "bitmap_frame_allocator" si usa il
+// BitmapFrameAllocator, che cerca i blocchi contigui nella bitmap.
+
+#[cfg(not(feature = "bitmap_frame_allocator"))]
+pub type KernelFrameAllocator = BuddyAllocator;
+#[cfg(feature = "bitmap_frame_allocator")]
+pub type KernelFrameAllocator = BitmapFrameAllocator;
+// ---
+
 // FRAME.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Frame {
@@ -66,3 +116,207 @@ pub trait FrameAllocator {
 }
 // ---
 
+// MEMORY CONTROLLER.
+
+pub struct MemoryController {
+	active_table: ActivePageTable,
+	frame_allocator: KernelFrameAllocator,
+	heap_end: usize,
+	slab_end: usize,
+	physical_window_end: usize,
+}
+
+impl MemoryController {
+	pub fn frame_allocator(&self) -> &KernelFrameAllocator {
+		&self.frame_allocator
+	}
+	
+	// Alloca 2^order frame contigui, allineati a 2^order frame.
+	pub fn allocate_frames(&mut self, order: usize) -> Option<Frame> {
+		self.frame_allocator.allocate_frames(order)
+	}
+	
+	pub fn deallocate_frames(&mut self, frame: Frame, order: usize) {
+		self.frame_allocator.deallocate_frames(frame, order);
+	}
+	
+	// Mappa fino a `pages` nuove pagine in fondo all'heap.
+	// Ritorna inizio e dimensione della zona mappata, None se l'heap ha
+	// raggiunto HEAP_MAX_SIZE o i frame sono finiti.
+	pub fn grow_heap(&mut self, pages: usize) -> Option<(usize, usize)> {
+		let start = self.heap_end;
+		if start + pages * PAGE_SIZE > HEAP_START + HEAP_MAX_SIZE {
+			return None;
+		}
+		
+		for _ in 0..pages {
+			let frame = match self.frame_allocator.allocate_frame() {
+				Some(frame) => frame,
+				None => break,
+			};
+			let page = Page::containing_address(self.heap_end);
+			self.active_table.map_to(page, frame, WRITEABLE | NO_EXECUTE,
+									 &mut self.frame_allocator);
+			self.heap_end += PAGE_SIZE;
+		}
+		
+		if self.heap_end == start {
+			None
+		}
+		else {
+			Some((start, self.heap_end - start))
+		}
+	}
+	
+	// Indirizzo fisico di `address` nella tabella attiva, None se non mappato.
+	pub fn translate(&self, address: VirtualAddress) -> Option<PhysicalAddress> {
+		self.active_table.translate(address)
+	}
+	
+	pub fn heap_size(&self) -> usize {
+		self.heap_end - HEAP_START
+	}
+	
+	// Mappa una nuova pagina per lo slab allocator.
+	pub fn alloc_slab_page(&mut self) -> Option<VirtualAddress> {
+		if self.slab_end + PAGE_SIZE > SLAB_START + SLAB_MAX_SIZE {
+			return None;
+		}
+		
+		let frame = match self.frame_allocator.allocate_frame() {
+			Some(frame) => frame,
+			None => return None,
+		};
+		let page_start = self.slab_end;
+		self.active_table.map_to(Page::containing_address(page_start), frame,
+								 WRITEABLE | NO_EXECUTE, &mut self.frame_allocator);
+		self.slab_end += PAGE_SIZE;
+		
+		Some(page_start)
+	}
+	
+	// Mappa `size` byte di memoria fisica da `address` nella finestra fisica,
+	// ad esempio registri MMIO (con NO_CACHE) o tabelle ACPI.
+	// I frame non appartengono all'allocatore e non vengono mai liberati.
+	// Le zone si tolgono con unmap_physical.
+	pub fn map_physical(&mut self, address: PhysicalAddress, size: usize,
+						flags: EntryFlags) -> VirtualAddress
+	{
+		let start_frame = Frame::containing_address(address);
+		let end_frame = Frame::containing_address(address + size - 1);
+		let pages = end_frame.number - start_frame.number + 1;
+		assert! (self.physical_window_end + pages * PAGE_SIZE <=
+				 PHYSICAL_WINDOW_START + PHYSICAL_WINDOW_SIZE,
+				 "Physical window full.");
+		
+		let window_start = self.physical_window_end;
+		for frame in Frame::range_inclusive(start_frame, end_frame) {
+			let page = Page::containing_address(self.physical_window_end);
+			self.active_table.map_to(page, frame, flags, &mut self.frame_allocator);
+			self.physical_window_end += PAGE_SIZE;
+		}
+		
+		window_start + address % PAGE_SIZE
+	}
+	
+	// Toglie il mapping di una zona ottenuta da map_physical con la stessa
+	// `size`. Se la zona è l'ultima mappata la finestra si accorcia, quindi
+	// le mappature temporanee vanno tolte in ordine inverso.
+	pub fn unmap_physical(&mut self, address: VirtualAddress, size: usize) {
+		let start_page = Page::containing_address(address);
+		let end_page = Page::containing_address(address + size - 1);
+		assert! (start_page.start_address() >= PHYSICAL_WINDOW_START &&
+				 end_page.start_address() < self.physical_window_end,
+				 "{:#x} is not in the physical window.", address);
+		
+		for page in Page::range_inclusive(start_page, end_page) {
+			self.active_table.unmap_without_free(page);
+		}
+		
+		if end_page.start_address() + PAGE_SIZE == self.physical_window_end {
+			self.physical_window_end = start_page.start_address();
+		}
+	}
+}
+
+static MEMORY_CONTROLLER: Mutex<Option<MemoryController>> = Mutex::new(None);
+
+// Esegue `f` sul MemoryController creato da init.
+// Dentro `f` non si deve allocare sull'heap (Box, Vec, format!...): se
+// l'heap deve crescere trova il MemoryController occupato e l'allocazione
+// fallisce. Meglio copiare i valori che servono e usarli dopo.
+pub fn with_memory_controller<F, T>(f: F) -> T
+	where F: FnOnce(&mut MemoryController) -> T
+{
+	let mut controller = MEMORY_CONTROLLER.lock();
+	f(controller.as_mut().expect("Memory controller not initialized."))
+}
+
+// Chiamata dall'allocatore dell'heap: prima di init l'heap non può crescere.
+// Usa try_lock: se il MemoryController è già preso (ad esempio da
+// with_memory_controller) un lock bloccante non tornerebbe mai.
+pub fn grow_heap(pages: usize) -> Option<(usize, usize)> {
+	MEMORY_CONTROLLER.try_lock()
+		.and_then(|mut controller| controller.as_mut()
+			.and_then(|controller| controller.grow_heap(pages)))
+}
+
+// Chiamata dallo slab allocator quando una cache è vuota. Come grow_heap,
+// senza attendere il lock.
+pub fn alloc_slab_page() -> Option<VirtualAddress> {
+	MEMORY_CONTROLLER.try_lock()
+		.and_then(|mut controller| controller.as_mut()
+			.and_then(|controller| controller.alloc_slab_page()))
+}
+// ---
+
+// INIT.
+
+// Effettua il remapping del kernel con l'allocatore di boot, poi crea
+// il KernelFrameAllocator che gestisce la memoria fisica da qui in avanti.
+// Da questo momento l'heap può crescere.
+pub fn init(boot_info: &BootInformation) {
+	let memory_map_tag = boot_info.memory_map_tag().expect("Memory tag required");
+	let elf_sections_tag = 	boot_info.elf_sections_tag()
+							.expect("Elf-sections tag required");
+
+	let kernel_start = elf_sections_tag.sections()
+					   .filter(|s| s.is_allocated()).map(|s| s.addr)
+					   .min().unwrap() as usize;
+	let kernel_end = elf_sections_tag.sections()
+					 .filter(|s| s.is_allocated()).map(|s| s.addr + s.size)
+					 .max().unwrap() as usize;
+
+	let multiboot_start = boot_info as *const _ as usize;
+	let multiboot_end = multiboot_start + (boot_info.total_size as usize);
+
+	let mut boot_allocator = AreaFrameAllocator::new(
+		kernel_start,
+		kernel_end,
+		multiboot_start,
+		multiboot_end,
+		memory_map_tag.memory_areas()
+	);
+
+	let mut active_table = paging::remap_the_kernel(&mut boot_allocator, boot_info);
+	boot_allocator.leave_identity_map();
+
+	let frame_allocator = KernelFrameAllocator::new(
+		kernel_start,
+		kernel_end,
+		multiboot_start,
+		multiboot_end,
+		memory_map_tag.memory_areas(),
+		&mut active_table,
+		&mut boot_allocator
+	);
+
+	*MEMORY_CONTROLLER.lock() = Some(MemoryController {
+		active_table: active_table,
+		frame_allocator: frame_allocator,
+		heap_end: HEAP_START,
+		slab_end: SLAB_START,
+		physical_window_end: PHYSICAL_WINDOW_START,
+	});
+}
+// ---
@@ -1,4 +1,6 @@
 use memory::Frame;
+use core::fmt;
+use multiboot2::ElfSection;
 
 pub struct Entry(u64);
 
@@ -34,6 +36,12 @@ impl Entry {
 	
 }
 
+impl fmt::Debug for Entry {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:#018x} ({:?})", self.0, self.flags())
+	}
+}
+
 bitflags! {
 	pub flags EntryFlags: u64 {
 		const PRESENT = 1 << 0,
@@ -48,3 +56,28 @@ bitflags! {
 		const NO_EXECUTE = 1 << 63,
 	}
 }
+
+// Flag delle sezioni ELF (campo sh_flags).
+const ELF_SECTION_WRITABLE: u64 = 0x1;
+const ELF_SECTION_ALLOCATED: u64 = 0x2;
+const ELF_SECTION_EXECUTABLE: u64 = 0x4;
+
+impl EntryFlags {
+	// Ricava i flag della pagina dai flag della sezione ELF:
+	// scrivibile solo se la sezione lo è, eseguibile solo se contiene codice.
+	pub fn from_elf_section_flags(section: &ElfSection) -> EntryFlags {
+		let mut flags = EntryFlags::empty();
+
+		if section.flags & ELF_SECTION_ALLOCATED != 0 {
+			flags = flags | PRESENT;
+		}
+		if section.flags & ELF_SECTION_WRITABLE != 0 {
+			flags = flags | WRITEABLE;
+		}
+		if section.flags & ELF_SECTION_EXECUTABLE == 0 {
+			flags = flags | NO_EXECUTE;
+		}
+
+		flags
+	}
+}
@@ -0,0 +1,152 @@
+use super::{VirtualAddress, PhysicalAddress, Page, ENTRY_COUNT};
+use super::entry::*;
+use super::table::{self, Table, Level4};
+use memory::{PAGE_SIZE, Frame, FrameAllocator};
+use core::ptr::NonNull;
+
+pub struct Mapper {
+	p4: NonNull<Table<Level4>>,
+}
+
+// La tabella P4 attiva si raggiunge solo attraverso il MemoryController.
+unsafe impl Send for Mapper {}
+
+impl Mapper {
+	pub unsafe fn new() -> Mapper {
+		Mapper {
+			p4: NonNull::new_unchecked(table::P4),
+		}
+	}
+
+	pub fn p4(&self) -> &Table<Level4> {
+		unsafe { self.p4.as_ref() }
+	}
+
+	pub fn p4_mut(&mut self) -> &mut Table<Level4> {
+		unsafe { self.p4.as_mut() }
+	}
+
+	// TRANSLATION.
+
+	// Traduce un indirizzo virtuale nel corrispondente indirizzo fisico.
+	// Ritorna None se l'indirizzo non è mappato.
+	pub fn translate(&self, virtual_address: VirtualAddress) -> Option<PhysicalAddress> {
+		let offset = virtual_address % PAGE_SIZE;
+		self.translate_page(Page::containing_address(virtual_address))
+			.map(|frame| frame.number * PAGE_SIZE + offset)
+	}
+
+	pub fn translate_page(&self, page: Page) -> Option<Frame> {
+		let p3 = self.p4().next_table(page.p4_index());
+
+		let huge_page = || {
+			p3.and_then(|p3| {
+				let p3_entry = &p3[page.p3_index()];
+				// Huge page da 1 GiB?
+				if let Some(start_frame) = p3_entry.pointed_frame() {
+					if p3_entry.flags().contains(HUGE_PAGE) {
+						// L'indirizzo deve essere allineato a 1 GiB.
+						assert! (start_frame.number % (ENTRY_COUNT * ENTRY_COUNT) == 0);
+						return Some(Frame {
+							number: start_frame.number +
+									page.p2_index() * ENTRY_COUNT +
+									page.p1_index(),
+						});
+					}
+				}
+
+				if let Some(p2) = p3.next_table(page.p3_index()) {
+					let p2_entry = &p2[page.p2_index()];
+					// Huge page da 2 MiB?
+					if let Some(start_frame) = p2_entry.pointed_frame() {
+						if p2_entry.flags().contains(HUGE_PAGE) {
+							// L'indirizzo deve essere allineato a 2 MiB.
+							assert! (start_frame.number % ENTRY_COUNT == 0);
+							return Some(Frame {
+								number: start_frame.number + page.p1_index()
+							});
+						}
+					}
+				}
+
+				None
+			})
+		};
+
+		p3.and_then(|p3| p3.next_table(page.p3_index()))
+		  .and_then(|p2| p2.next_table(page.p2_index()))
+		  .and_then(|p1| p1[page.p1_index()].pointed_frame())
+		  .or_else(huge_page)
+	}
+
+	// ---
+
+	// MAPPING.
+
+	// Mappa la pagina sul frame indicato, creando le tabelle intermedie
+	// mancanti con frame presi dall'allocatore.
+	pub fn map_to<A>(&mut self, page: Page, frame: Frame, flags: EntryFlags, allocator: &mut A)
+		where A: FrameAllocator
+	{
+		let p4 = self.p4_mut();
+		let p3 = p4.next_table_create(page.p4_index(), allocator);
+		let p2 = p3.next_table_create(page.p3_index(), allocator);
+		let p1 = p2.next_table_create(page.p2_index(), allocator);
+
+		assert! (p1[page.p1_index()].is_unused(),
+				 "Page {:?} already mapped.", page);
+		p1[page.p1_index()].set(frame, flags | PRESENT);
+	}
+
+	// Mappa la pagina su un frame libero qualsiasi.
+	pub fn map<A>(&mut self, page: Page, flags: EntryFlags, allocator: &mut A)
+		where A: FrameAllocator
+	{
+		let frame = allocator.allocate_frame().expect("No more frames.");
+		self.map_to(page, frame, flags, allocator)
+	}
+
+	// Mappa il frame sulla pagina con lo stesso indirizzo.
+	pub fn identity_map<A>(&mut self, frame: Frame, flags: EntryFlags, allocator: &mut A)
+		where A: FrameAllocator
+	{
+		let page = Page::containing_address(frame.start_address());
+		self.map_to(page, frame, flags, allocator)
+	}
+
+	// ---
+
+	// UNMAPPING.
+
+	// Rimuove il mapping della pagina e restituisce il frame all'allocatore.
+	pub fn unmap<A>(&mut self, page: Page, allocator: &mut A)
+		where A: FrameAllocator
+	{
+		let frame = self.unmap_without_free(page);
+		allocator.deallocate_frame(frame);
+	}
+
+	// Rimuove il mapping della pagina senza liberare il frame, che viene
+	// restituito al chiamante.
+	// TODO: liberare le tabelle P1, P2 e P3 quando diventano vuote.
+	pub fn unmap_without_free(&mut self, page: Page) -> Frame {
+		use x86::tlb;
+
+		assert! (self.translate(page.start_address()).is_some(),
+				 "Page {:?} not mapped.", page);
+
+		let p1 = self.p4_mut()
+					 .next_table_mut(page.p4_index())
+					 .and_then(|p3| p3.next_table_mut(page.p3_index()))
+					 .and_then(|p2| p2.next_table_mut(page.p2_index()))
+					 .expect("Mapping code does not support huge pages");
+
+		let frame = p1[page.p1_index()].pointed_frame().unwrap();
+		p1[page.p1_index()].set_unused();
+		unsafe { tlb::flush(page.start_address()) };
+
+		frame
+	}
+
+	// ---
+}
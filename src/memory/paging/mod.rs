@@ -1,13 +1,11 @@
-extern crate x86;
-
 pub use self::entry::*;
 pub use self::mapper::Mapper;
 
 use self::temporary_page::TemporaryPage;
 use self::table::{Table, Level4};
 
-use core::ptr::Unique;
 use core::ops::{Deref, DerefMut};
+use core::fmt;
 
 use memory::{PAGE_SIZE, Frame, FrameAllocator};
 
@@ -25,6 +23,7 @@ pub type PhysicalAddress = usize;
 pub type VirtualAddress = usize;
 
 
+
 // TESTING.
 
 pub fn test_paging<A>(allocator: &mut A)
@@ -96,7 +95,7 @@ impl Page {
 		Page { number: address / PAGE_SIZE }
 	}
 	
-	fn start_address(&self) -> usize {
+	pub fn start_address(&self) -> usize {
 		self.number * PAGE_SIZE
 	}
 	
@@ -115,6 +114,81 @@ impl Page {
 	fn p1_index(&self) -> usize {
 		(self.number >> 0) & 0o777
 	}
+	
+	pub fn range_inclusive(start: Page, end: Page) -> PageIter {
+		PageIter {
+			start: start,
+			end: end,
+		}
+	}
+}
+// ---
+
+
+// PAGE ITERATOR.
+
+pub struct PageIter {
+	start: Page,
+	end: Page,
+}
+
+impl Iterator for PageIter {
+	type Item = Page;
+	
+	fn next(&mut self) -> Option<Page> {
+		if self.start.number <= self.end.number {
+			let page = self.start;
+			self.start.number += 1;
+			Some(page)
+		}
+		else {
+			None
+		}
+	}
+}
+// ---
+
+
+// PAGE WALK.
+// Entry della tabella delle pagine corrente attraversate per tradurre
+// un indirizzo, lette attraverso il mapping ricorsivo.
+
+pub struct PageWalk {
+	address: VirtualAddress,
+}
+
+impl PageWalk {
+	pub fn new(address: VirtualAddress) -> PageWalk {
+		PageWalk { address: address }
+	}
+}
+
+impl fmt::Display for PageWalk {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// Niente Page::containing_address: l'indirizzo può non essere valido.
+		let page = Page { number: self.address / PAGE_SIZE };
+		
+		let p4 = unsafe { &*table::P4 };
+		try!(writeln!(f, "P4[{}] = {:?}", page.p4_index(), p4[page.p4_index()]));
+		
+		let p3 = match p4.next_table(page.p4_index()) {
+			Some(p3) => p3,
+			None => return Ok(()),
+		};
+		try!(writeln!(f, "P3[{}] = {:?}", page.p3_index(), p3[page.p3_index()]));
+		
+		let p2 = match p3.next_table(page.p3_index()) {
+			Some(p2) => p2,
+			None => return Ok(()),
+		};
+		try!(writeln!(f, "P2[{}] = {:?}", page.p2_index(), p2[page.p2_index()]));
+		
+		let p1 = match p2.next_table(page.p2_index()) {
+			Some(p1) => p1,
+			None => return Ok(()),
+		};
+		writeln!(f, "P1[{}] = {:?}", page.p1_index(), p1[page.p1_index()])
+	}
 }
 // ---
 
@@ -152,7 +226,7 @@ impl ActivePageTable {
 					f: F)
 		 where F: FnOnce(&mut Mapper)
 	{
-		 use self::x86::{controlregs, tlb};
+		 use x86::{controlregs, tlb};
 		 let flush_tlb = || unsafe { tlb::flush_all() };
 	 
 		{
@@ -168,7 +242,8 @@ impl ActivePageTable {
 
 			f(self);
 
-			self.p4_mut()[511].set(table.p4_frame.clone(), PRESENT | WRITEABLE);
+			// Ripristina il mapping ricorsivo sulla tabella p4 corrente.
+			p4_table[511].set(backup, PRESENT | WRITEABLE);
 			flush_tlb();
 		}
 		 
@@ -176,7 +251,7 @@ impl ActivePageTable {
 	}
 	 
 	pub fn switch(&mut self, new_table: InactivePageTable) -> InactivePageTable {
-        use self::x86::controlregs;
+        use x86::controlregs;
 
         let old_table = InactivePageTable {
             p4_frame: Frame::containing_address(unsafe { controlregs::cr3() } as usize),
@@ -220,6 +295,7 @@ impl InactivePageTable {
 // KERNEL REMAPPING.
 
 pub fn remap_the_kernel<A>(allocator: &mut A, boot_info: &BootInformation)
+	-> ActivePageTable
 	where A: FrameAllocator
 {
 	let mut temporary_page = TemporaryPage::new( Page{ number: 0xcafebabe }, 
@@ -236,8 +312,6 @@ pub fn remap_the_kernel<A>(allocator: &mut A, boot_info: &BootInformation)
 										.expect("Memory map tag required.");
 										
 		for section in elf_sections_tag.sections() {
-			use self::entry::WRITEABLE;
-			
 			if !section.is_allocated() {
 				// La sezione non è caricata in memoria.
 				continue; 
@@ -249,7 +323,7 @@ pub fn remap_the_kernel<A>(allocator: &mut A, boot_info: &BootInformation)
 						section.addr,
 						section.size);
 						
-			let flags = WRITEABLE;
+			let flags = EntryFlags::from_elf_section_flags(section);
 			
 			let start_frame = Frame::containing_address(section.start_address());
 			let end_frame = Frame::containing_address(section.end_address() - 1);
@@ -257,7 +331,36 @@ pub fn remap_the_kernel<A>(allocator: &mut A, boot_info: &BootInformation)
 				mapper.identity_map(frame, flags, allocator);
 			}
 		}
+		
+		// Identity map del VGA text buffer.
+		let vga_buffer_frame = Frame::containing_address(0xb8000);
+		mapper.identity_map(vga_buffer_frame, WRITEABLE | NO_EXECUTE, allocator);
+		
+		// Identity map delle informazioni di multiboot (sola lettura).
+		let multiboot_start = boot_info as *const _ as usize;
+		let multiboot_end = multiboot_start + (boot_info.total_size as usize);
+		
+		let multiboot_start = Frame::containing_address(multiboot_start);
+		let multiboot_end = Frame::containing_address(multiboot_end - 1);
+		for frame in Frame::range_inclusive(multiboot_start, multiboot_end) {
+			mapper.identity_map(frame, NO_EXECUTE, allocator);
+		}
 	});
+	
+	let old_table = active_table.switch(new_table);
+	println! ("Switched to the new page table.");
+	
+	// In boot.asm la vecchia tabella P4 precede P3 e P2, che stanno subito
+	// sotto lo stack. Dopo lo switch nessuna delle tre è più in uso: uno
+	// stack overflow sovrascrive prima P2 e P3, poi arriva alla pagina di P4.
+	// Togliendone il mapping diventa una guard page, così l'overflow
+	// provoca un page fault invece di sovrascrivere il resto della .bss.
+	// Il frame fa parte del kernel e non va restituito all'allocatore.
+	let old_p4_page = Page::containing_address(old_table.p4_frame.start_address());
+	active_table.unmap_without_free(old_p4_page);
+	println! ("Guard page at {:#x}.", old_p4_page.start_address());
+	
+	active_table
 }
 
 // ---
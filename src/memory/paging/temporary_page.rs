@@ -0,0 +1,93 @@
+use super::{Page, ActivePageTable, VirtualAddress};
+use super::table::{Table, Level1};
+use super::entry::WRITEABLE;
+use memory::{Frame, FrameAllocator};
+
+// TEMPORARY PAGE.
+// Pagina usata per mappare temporaneamente un frame qualsiasi, ad esempio
+// la tabella P4 di una InactivePageTable.
+
+pub struct TemporaryPage {
+	page: Page,
+	allocator: TinyAllocator,
+}
+
+impl TemporaryPage {
+	pub fn new<A>(page: Page, allocator: &mut A) -> TemporaryPage
+		where A: FrameAllocator
+	{
+		TemporaryPage {
+			page: page,
+			allocator: TinyAllocator::new(allocator),
+		}
+	}
+
+	// Mappa la pagina temporanea sul frame indicato.
+	// Ritorna l'indirizzo virtuale di inizio della pagina.
+	pub fn map(&mut self, frame: Frame, active_table: &mut ActivePageTable)
+		-> VirtualAddress
+	{
+		assert! (active_table.translate_page(self.page).is_none(),
+				 "Temporary page is already mapped.");
+
+		active_table.map_to(self.page, frame, WRITEABLE, &mut self.allocator);
+		self.page.start_address()
+	}
+
+	// Mappa la pagina temporanea sul frame indicato e la restituisce
+	// come tabella di livello 1, così da poterne modificare le entry.
+	pub fn map_table_frame(&mut self,
+						   frame: Frame,
+						   active_table: &mut ActivePageTable)
+		-> &mut Table<Level1>
+	{
+		unsafe { &mut *(self.map(frame, active_table) as *mut Table<Level1>) }
+	}
+
+	// Rimuove il mapping della pagina temporanea.
+	// Il frame mappato non appartiene alla pagina e non viene liberato.
+	pub fn unmap(&mut self, active_table: &mut ActivePageTable) {
+		active_table.unmap_without_free(self.page);
+	}
+}
+
+// ---
+
+// TINY ALLOCATOR.
+// Mappare la pagina temporanea richiede al massimo tre tabelle (P3, P2, P1),
+// quindi bastano tre frame presi una volta sola dall'allocatore principale.
+
+struct TinyAllocator([Option<Frame>; 3]);
+
+impl TinyAllocator {
+	fn new<A>(allocator: &mut A) -> TinyAllocator
+		where A: FrameAllocator
+	{
+		let mut f = || allocator.allocate_frame();
+		let frames = [f(), f(), f()];
+		TinyAllocator(frames)
+	}
+}
+
+impl FrameAllocator for TinyAllocator {
+	fn allocate_frame(&mut self) -> Option<Frame> {
+		for frame_option in &mut self.0 {
+			if frame_option.is_some() {
+				return frame_option.take();
+			}
+		}
+		None
+	}
+
+	fn deallocate_frame(&mut self, frame: Frame) {
+		for frame_option in &mut self.0 {
+			if frame_option.is_none() {
+				*frame_option = Some(frame);
+				return;
+			}
+		}
+		panic!("Tiny allocator can hold only 3 frames.");
+	}
+}
+
+// ---
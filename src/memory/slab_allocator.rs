@@ -0,0 +1,150 @@
+use core::alloc::Layout;
+use core::ptr;
+use spin::Mutex;
+use memory::{self, PAGE_SIZE};
+
+// Dimensioni degli oggetti delle cache, da 8 B a 2 KiB.
+pub const OBJECT_SIZES: [usize; CACHE_COUNT] = [8, 16, 32, 64, 128, 256, 512, 1024, 2048];
+pub const CACHE_COUNT: usize = 9;
+
+pub static SLAB_ALLOCATOR: Mutex<SlabAllocator> = Mutex::new(SlabAllocator::new());
+
+// Indice della cache adatta a `layout`, None se l'oggetto è troppo grande.
+// Gli oggetti sono allineati alla propria dimensione dentro la pagina.
+pub fn size_class(layout: &Layout) -> Option<usize> {
+	let size = layout.size().max(layout.align());
+	OBJECT_SIZES.iter().position(|&object_size| size <= object_size)
+}
+
+// FREE OBJECT.
+// Un oggetto libero contiene il puntatore al successivo oggetto libero
+// della stessa cache.
+
+struct FreeObject {
+	next: *mut FreeObject,
+}
+
+// ---
+
+// SLAB CACHE.
+// Oggetti di una sola dimensione, ricavati da pagine da 4 KiB mappate
+// nella zona SLAB_START. Le pagine non vengono restituite: gli oggetti
+// liberati tornano nella lista della cache.
+
+pub struct SlabCache {
+	object_size: usize,
+	free_list: *mut FreeObject,
+	pages: usize,
+	used_objects: usize,
+	free_objects: usize,
+}
+
+impl SlabCache {
+	const fn new(object_size: usize) -> SlabCache {
+		SlabCache {
+			object_size: object_size,
+			free_list: 0 as *mut FreeObject,
+			pages: 0,
+			used_objects: 0,
+			free_objects: 0,
+		}
+	}
+
+	pub fn object_size(&self) -> usize {
+		self.object_size
+	}
+
+	pub fn pages(&self) -> usize {
+		self.pages
+	}
+
+	pub fn used_objects(&self) -> usize {
+		self.used_objects
+	}
+
+	pub fn free_objects(&self) -> usize {
+		self.free_objects
+	}
+
+	fn allocate(&mut self) -> *mut u8 {
+		if self.free_list.is_null() && !self.grow() {
+			return ptr::null_mut();
+		}
+
+		let object = self.free_list;
+		self.free_list = unsafe { (*object).next };
+		self.used_objects += 1;
+		self.free_objects -= 1;
+		object as *mut u8
+	}
+
+	fn deallocate(&mut self, address: *mut u8) {
+		let object = address as *mut FreeObject;
+		unsafe { ptr::write(object, FreeObject { next: self.free_list }) };
+		self.free_list = object;
+		self.used_objects -= 1;
+		self.free_objects += 1;
+	}
+
+	// Divide una nuova pagina in oggetti e li aggiunge alla lista libera.
+	fn grow(&mut self) -> bool {
+		let page_start = match memory::alloc_slab_page() {
+			Some(address) => address,
+			None => return false,
+		};
+
+		for index in (0..PAGE_SIZE / self.object_size).rev() {
+			let object = (page_start + index * self.object_size) as *mut FreeObject;
+			unsafe { ptr::write(object, FreeObject { next: self.free_list }) };
+			self.free_list = object;
+			self.free_objects += 1;
+		}
+		self.pages += 1;
+
+		true
+	}
+}
+
+// ---
+
+// SLAB ALLOCATOR.
+
+pub struct SlabAllocator {
+	caches: [SlabCache; CACHE_COUNT],
+}
+
+// Le liste libere stanno nelle pagine delle cache, protette dal Mutex.
+unsafe impl Send for SlabAllocator {}
+
+impl SlabAllocator {
+	// Una cache per ogni OBJECT_SIZES: nelle const fn non ci sono cicli.
+	const fn new() -> SlabAllocator {
+		SlabAllocator {
+			caches: [
+				SlabCache::new(OBJECT_SIZES[0]),
+				SlabCache::new(OBJECT_SIZES[1]),
+				SlabCache::new(OBJECT_SIZES[2]),
+				SlabCache::new(OBJECT_SIZES[3]),
+				SlabCache::new(OBJECT_SIZES[4]),
+				SlabCache::new(OBJECT_SIZES[5]),
+				SlabCache::new(OBJECT_SIZES[6]),
+				SlabCache::new(OBJECT_SIZES[7]),
+				SlabCache::new(OBJECT_SIZES[8]),
+			],
+		}
+	}
+
+	pub fn caches(&self) -> &[SlabCache] {
+		&self.caches
+	}
+
+	pub fn allocate(&mut self, class: usize) -> *mut u8 {
+		self.caches[class].allocate()
+	}
+
+	pub fn deallocate(&mut self, address: *mut u8, class: usize) {
+		self.caches[class].deallocate(address)
+	}
+}
+
+// ---
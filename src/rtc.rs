@@ -0,0 +1,138 @@
+use core::fmt;
+use x86::io::{inb, outb};
+
+// RTC CMOS.
+// Si scrive l'indice del registro su 0x70 e si legge il valore da 0x71.
+// Il bit 7 dell'indice disabilita le NMI: resta a zero.
+
+const CMOS_ADDRESS: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+
+const REGISTER_SECONDS: u8 = 0x00;
+const REGISTER_MINUTES: u8 = 0x02;
+const REGISTER_HOURS: u8 = 0x04;
+const REGISTER_DAY: u8 = 0x07;
+const REGISTER_MONTH: u8 = 0x08;
+const REGISTER_YEAR: u8 = 0x09;
+// Non standard, ma presente su QEMU e sulla maggior parte dei PC.
+const REGISTER_CENTURY: u8 = 0x32;
+const REGISTER_STATUS_A: u8 = 0x0a;
+const REGISTER_STATUS_B: u8 = 0x0b;
+
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 1 << 7;
+const STATUS_B_24_HOUR: u8 = 1 << 1;
+const STATUS_B_BINARY: u8 = 1 << 2;
+// In modalità 12 ore il bit 7 delle ore indica il pomeriggio.
+const HOURS_PM: u8 = 1 << 7;
+
+const DEFAULT_CENTURY: u16 = 20;
+
+// DATE TIME.
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+	pub year: u16,
+	pub month: u8,
+	pub day: u8,
+	pub hour: u8,
+	pub minute: u8,
+	pub second: u8,
+}
+
+impl fmt::Display for DateTime {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+			   self.year, self.month, self.day,
+			   self.hour, self.minute, self.second)
+	}
+}
+
+// ---
+
+// READ.
+
+// Registri così come li restituisce l'RTC, prima della conversione.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct RawDateTime {
+	second: u8,
+	minute: u8,
+	hour: u8,
+	day: u8,
+	month: u8,
+	year: u8,
+	century: u8,
+}
+
+// Data e ora correnti (l'RTC di solito è in ora locale).
+pub fn now() -> DateTime {
+	// Durante un aggiornamento i registri possono essere incoerenti:
+	// si aspetta che finisca e si rilegge finché due letture coincidono.
+	let mut last = read_raw();
+	loop {
+		let current = read_raw();
+		if current == last {
+			break;
+		}
+		last = current;
+	}
+
+	decode(last, read_register(REGISTER_STATUS_B))
+}
+
+fn read_raw() -> RawDateTime {
+	while read_register(REGISTER_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0 {}
+
+	RawDateTime {
+		second: read_register(REGISTER_SECONDS),
+		minute: read_register(REGISTER_MINUTES),
+		hour: read_register(REGISTER_HOURS),
+		day: read_register(REGISTER_DAY),
+		month: read_register(REGISTER_MONTH),
+		year: read_register(REGISTER_YEAR),
+		century: read_register(REGISTER_CENTURY),
+	}
+}
+
+fn decode(raw: RawDateTime, status_b: u8) -> DateTime {
+	let binary = status_b & STATUS_B_BINARY != 0;
+	let convert = |value: u8| if binary { value } else { from_bcd(value) };
+
+	let pm = raw.hour & HOURS_PM != 0;
+	let mut hour = convert(raw.hour & !HOURS_PM);
+	if status_b & STATUS_B_24_HOUR == 0 {
+		// 12 AM è mezzanotte, 12 PM è mezzogiorno.
+		hour = match (hour, pm) {
+			(12, false) => 0,
+			(12, true) => 12,
+			(hour, true) => hour + 12,
+			(hour, false) => hour,
+		};
+	}
+
+	let century = match convert(raw.century) as u16 {
+		century @ 19...21 => century,
+		_ => DEFAULT_CENTURY,
+	};
+
+	DateTime {
+		year: century * 100 + convert(raw.year) as u16,
+		month: convert(raw.month),
+		day: convert(raw.day),
+		hour: hour,
+		minute: convert(raw.minute),
+		second: convert(raw.second),
+	}
+}
+
+fn from_bcd(value: u8) -> u8 {
+	(value >> 4) * 10 + (value & 0x0f)
+}
+
+fn read_register(register: u8) -> u8 {
+	unsafe {
+		outb(CMOS_ADDRESS, register);
+		inb(CMOS_DATA)
+	}
+}
+
+// ---
@@ -0,0 +1,199 @@
+use core::fmt;
+use spin::Mutex;
+use x86::io::{inb, outb};
+use interrupts::{self, irq};
+
+// MACROS.
+macro_rules! serial_print {
+    ($($arg:tt)*) => ({
+            $crate::interrupts::without_interrupts(|| {
+                use core::fmt::Write;
+                let mut port = $crate::serial::COM1.lock();
+                port.write_fmt(format_args!($($arg)*)).unwrap();
+            });
+    });
+}
+
+macro_rules! serial_println {
+    ($fmt:expr) => (serial_print!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => (serial_print!(concat!($fmt, "\n"), $($arg)*));
+}
+// ---
+
+// UART 16550.
+// Registri a partire dalla porta base. Con DLAB (bit 7 di LINE_CONTROL)
+// i primi due diventano il divisore del baud rate.
+
+const COM1_BASE: u16 = 0x3f8;
+const COM1_IRQ: u8 = 4;
+
+const DATA: u16 = 0;
+const INTERRUPT_ENABLE: u16 = 1;
+const DIVISOR_LOW: u16 = 0;
+const DIVISOR_HIGH: u16 = 1;
+const FIFO_CONTROL: u16 = 2;
+const LINE_CONTROL: u16 = 3;
+const MODEM_CONTROL: u16 = 4;
+const LINE_STATUS: u16 = 5;
+const SCRATCH: u16 = 7;
+
+const UART_CLOCK: u32 = 115200;
+pub const DEFAULT_BAUD_RATE: u32 = 115200;
+
+const LINE_8N1: u8 = 0x03;
+const LINE_DLAB: u8 = 1 << 7;
+// FIFO abilitate e svuotate, interrupt a 14 byte.
+const FIFO_ENABLE_CLEAR_14: u8 = 0xc7;
+// DTR, RTS e OUT2 (che collega l'interrupt della UART al PIC).
+const MODEM_DTR_RTS_OUT2: u8 = 0x0b;
+const INTERRUPT_DATA_AVAILABLE: u8 = 1 << 0;
+
+const STATUS_DATA_READY: u8 = 1 << 0;
+const STATUS_TRANSMIT_EMPTY: u8 = 1 << 5;
+
+const RECEIVE_BUFFER_SIZE: usize = 256;
+
+pub static COM1: Mutex<SerialPort> = Mutex::new(SerialPort::new(COM1_BASE));
+
+pub struct SerialPort {
+	base: u16,
+	present: bool,
+	// Byte ricevuti dall'handler della IRQ, in attesa di essere letti.
+	received: [u8; RECEIVE_BUFFER_SIZE],
+	received_start: usize,
+	received_count: usize,
+}
+
+impl SerialPort {
+	const fn new(base: u16) -> SerialPort {
+		SerialPort {
+			base: base,
+			present: false,
+			received: [0; RECEIVE_BUFFER_SIZE],
+			received_start: 0,
+			received_count: 0,
+		}
+	}
+
+	// 8N1 al baud rate richiesto, FIFO attive, interrupt spenti.
+	// Se la porta non risponde, le scritture vengono scartate.
+	pub fn init(&mut self, baud_rate: u32) {
+		// Una UART assente non ricorda il valore dello scratch register.
+		self.write_register(SCRATCH, 0xae);
+		self.present = self.read_register(SCRATCH) == 0xae;
+		if !self.present {
+			return;
+		}
+
+		let divisor = UART_CLOCK / baud_rate;
+		self.write_register(INTERRUPT_ENABLE, 0);
+		self.write_register(LINE_CONTROL, LINE_DLAB);
+		self.write_register(DIVISOR_LOW, divisor as u8);
+		self.write_register(DIVISOR_HIGH, (divisor >> 8) as u8);
+		self.write_register(LINE_CONTROL, LINE_8N1);
+		self.write_register(FIFO_CONTROL, FIFO_ENABLE_CLEAR_14);
+		self.write_register(MODEM_CONTROL, MODEM_DTR_RTS_OUT2);
+	}
+
+	pub fn is_present(&self) -> bool {
+		self.present
+	}
+
+	pub fn write_byte(&mut self, byte: u8) {
+		if !self.present {
+			return;
+		}
+
+		while self.read_register(LINE_STATUS) & STATUS_TRANSMIT_EMPTY == 0 {}
+		self.write_register(DATA, byte);
+	}
+
+	// Byte ricevuto, se c'è: prima quelli raccolti dall'interrupt,
+	// poi direttamente dalla UART.
+	pub fn read_byte(&mut self) -> Option<u8> {
+		if self.received_count > 0 {
+			let byte = self.received[self.received_start];
+			self.received_start = (self.received_start + 1) % RECEIVE_BUFFER_SIZE;
+			self.received_count -= 1;
+			return Some(byte);
+		}
+
+		self.poll_byte()
+	}
+
+	fn poll_byte(&mut self) -> Option<u8> {
+		if self.present && self.read_register(LINE_STATUS) & STATUS_DATA_READY != 0 {
+			Some(self.read_register(DATA))
+		}
+		else {
+			None
+		}
+	}
+
+	// Svuota la FIFO di ricezione nel buffer. Se è pieno si perdono
+	// i byte più vecchi.
+	fn receive(&mut self) {
+		while let Some(byte) = self.poll_byte() {
+			let end = (self.received_start + self.received_count) % RECEIVE_BUFFER_SIZE;
+			self.received[end] = byte;
+			if self.received_count == RECEIVE_BUFFER_SIZE {
+				self.received_start = (self.received_start + 1) % RECEIVE_BUFFER_SIZE;
+			}
+			else {
+				self.received_count += 1;
+			}
+		}
+	}
+
+	fn read_register(&self, register: u16) -> u8 {
+		unsafe { inb(self.base + register) }
+	}
+
+	fn write_register(&self, register: u16, value: u8) {
+		unsafe { outb(self.base + register, value) };
+	}
+}
+
+impl fmt::Write for SerialPort {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		for byte in s.bytes() {
+			// I terminali vogliono \r\n.
+			if byte == b'\n' {
+				self.write_byte(b'\r');
+			}
+			self.write_byte(byte);
+		}
+		Ok(())
+	}
+}
+
+// ---
+
+// INIT.
+
+pub fn init() {
+	COM1.lock().init(DEFAULT_BAUD_RATE);
+}
+
+// Attiva l'interrupt di ricezione: i byte arrivati vengono raccolti
+// dall'handler della IRQ 4 e letti con read_byte.
+pub fn enable_receive_interrupt() {
+	if !interrupts::without_interrupts(|| COM1.lock().is_present()) {
+		return;
+	}
+
+	irq::register(COM1_IRQ, serial_handler);
+	interrupts::without_interrupts(|| {
+		COM1.lock().write_register(INTERRUPT_ENABLE, INTERRUPT_DATA_AVAILABLE);
+	});
+}
+
+fn serial_handler(_irq: u8) {
+	COM1.lock().receive();
+}
+
+pub fn read_byte() -> Option<u8> {
+	interrupts::without_interrupts(|| COM1.lock().read_byte())
+}
+
+// ---
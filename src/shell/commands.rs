@@ -0,0 +1,107 @@
+use multiboot2;
+use cpu;
+use interrupts;
+use memory::{self, PageWalk};
+use super::{Shell, ShellOutput};
+
+// COMMANDS.
+
+pub struct Command {
+	pub name: &'static str,
+	pub help: &'static str,
+	pub run: fn(shell: &mut Shell, arguments: &[&str]),
+}
+
+static COMMANDS: [Command; 7] = [
+	Command { name: "help", help: "list the commands", run: help },
+	Command { name: "meminfo", help: "memory areas and allocator usage", run: meminfo },
+	Command { name: "translate", help: "translate <vaddr>: virtual to physical address", run: translate },
+	Command { name: "sections", help: "kernel ELF sections", run: sections },
+	Command { name: "clear", help: "clear the screen", run: clear },
+	Command { name: "reboot", help: "restart the machine", run: reboot },
+	Command { name: "halt", help: "stop the machine", run: halt },
+];
+
+pub fn find(name: &str) -> Option<&'static Command> {
+	COMMANDS.iter().find(|command| command.name == name)
+}
+
+pub fn all() -> &'static [Command] {
+	&COMMANDS
+}
+
+// ---
+
+fn help(_shell: &mut Shell, _arguments: &[&str]) {
+	for command in COMMANDS.iter() {
+		shell_println!("    {:<10} {}", command.name, command.help);
+	}
+}
+
+fn meminfo(shell: &mut Shell, _arguments: &[&str]) {
+	let boot_info = unsafe { multiboot2::load(shell.multiboot_info_pointer()) };
+
+	::print_multiboot_info(&mut ShellOutput, boot_info).unwrap();
+	::print_frame_info(&mut ShellOutput).unwrap();
+	::print_heap_info(&mut ShellOutput).unwrap();
+	::print_slab_info(&mut ShellOutput).unwrap();
+}
+
+fn translate(_shell: &mut Shell, arguments: &[&str]) {
+	let address = match arguments.first().and_then(|argument| parse_address(argument)) {
+		Some(address) => address,
+		None => {
+			shell_println!("Usage: translate <vaddr> (decimal or 0x hex)");
+			return;
+		}
+	};
+
+	// Fuori dalla metà alta o bassa lo spazio virtuale non esiste.
+	if address >= 0x0000_8000_0000_0000 && address < 0xffff_8000_0000_0000 {
+		shell_println!("0x{:x} is not a canonical address", address);
+		return;
+	}
+
+	match memory::with_memory_controller(|controller| controller.translate(address)) {
+		Some(physical) => shell_println!("0x{:x} -> 0x{:x}", address, physical),
+		None => shell_println!("0x{:x} is not mapped", address),
+	}
+	shell_print!("{}", PageWalk::new(address));
+}
+
+fn sections(shell: &mut Shell, _arguments: &[&str]) {
+	let boot_info = unsafe { multiboot2::load(shell.multiboot_info_pointer()) };
+	::print_kernel_sections(&mut ShellOutput, boot_info).unwrap();
+}
+
+fn clear(_shell: &mut Shell, _arguments: &[&str]) {
+	// Schermo pulito e cursore in alto a sinistra, su VGA e seriale.
+	shell_print!("\x1b[2J\x1b[H");
+}
+
+fn reboot(_shell: &mut Shell, _arguments: &[&str]) {
+	shell_println!("Rebooting...");
+	cpu::reboot();
+}
+
+fn halt(_shell: &mut Shell, _arguments: &[&str]) {
+	shell_println!("System halted. You can turn off the machine.");
+	interrupts::disable();
+	loop {
+		cpu::halt();
+	}
+}
+
+// ---
+
+fn parse_address(argument: &str) -> Option<usize> {
+	let result = if argument.starts_with("0x") {
+		usize::from_str_radix(&argument[2..], 16)
+	}
+	else {
+		usize::from_str_radix(argument, 10)
+	};
+	result.ok()
+}
+
+// ---
@@ -0,0 +1,310 @@
+use core::fmt;
+use alloc::string::String;
+use alloc::vec::Vec;
+use cpu;
+use keyboard::{self, KeyCode, KeyEvent};
+use serial;
+use vga_buffer;
+
+// MACROS.
+// L'output della shell va sia sulla VGA sia sulla seriale.
+macro_rules! shell_print {
+    ($($arg:tt)*) => ({
+            print!($($arg)*);
+            serial_print!($($arg)*);
+    });
+}
+
+macro_rules! shell_println {
+    ($fmt:expr) => (shell_print!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => (shell_print!(concat!($fmt, "\n"), $($arg)*));
+}
+// ---
+
+mod commands;
+
+const PROMPT: &'static str = "degrad> ";
+const HISTORY_SIZE: usize = 32;
+
+// INPUT.
+// Tasti dalla tastiera PS/2 e byte dalla seriale, ridotti agli stessi
+// comandi di editing.
+
+enum Input {
+	Char(char),
+	Enter,
+	Backspace,
+	Tab,
+	HistoryPrevious,
+	HistoryNext,
+}
+
+fn keyboard_input(event: KeyEvent) -> Option<Input> {
+	if !event.pressed {
+		return None;
+	}
+
+	match (event.code, event.character) {
+		(KeyCode::Up, _) => Some(Input::HistoryPrevious),
+		(KeyCode::Down, _) => Some(Input::HistoryNext),
+		(_, Some('\n')) => Some(Input::Enter),
+		(_, Some('\x08')) => Some(Input::Backspace),
+		(_, Some('\t')) => Some(Input::Tab),
+		(_, Some(character)) if !character.is_control() => Some(Input::Char(character)),
+		_ => None,
+	}
+}
+
+// Sequenze dei terminali: ESC [ A e ESC [ B sono le frecce su e giù.
+#[derive(Clone, Copy, PartialEq)]
+enum SerialState {
+	Normal,
+	Escape,
+	ControlSequence,
+	// Dopo \r alcuni terminali mandano anche \n.
+	CarriageReturn,
+}
+
+// ---
+
+// SHELL.
+
+pub struct Shell {
+	line: String,
+	history: Vec<String>,
+	// Posizione nella history durante la navigazione con le frecce.
+	history_index: Option<usize>,
+	serial_state: SerialState,
+	multiboot_info_pointer: usize,
+}
+
+impl Shell {
+	pub fn new(multiboot_info_pointer: usize) -> Shell {
+		Shell {
+			line: String::new(),
+			history: Vec::new(),
+			history_index: None,
+			serial_state: SerialState::Normal,
+			multiboot_info_pointer: multiboot_info_pointer,
+		}
+	}
+
+	// Non ritorna: fra un input e l'altro la CPU dorme.
+	pub fn run(&mut self) -> ! {
+		// Ora è la shell a stampare ciò che viene digitato.
+		keyboard::set_echo(false);
+		// Il cursore hardware segue il prompt.
+		vga_buffer::show_cursor();
+
+		shell_println!("");
+		shell_println!("Type 'help' for the list of commands.");
+		shell_print!("{}", PROMPT);
+
+		loop {
+			let mut idle = true;
+
+			while let Some(event) = keyboard::read_event() {
+				idle = false;
+				if let Some(input) = keyboard_input(event) {
+					self.handle(input);
+				}
+			}
+
+			while let Some(byte) = serial::read_byte() {
+				idle = false;
+				if let Some(input) = self.serial_input(byte) {
+					self.handle(input);
+				}
+			}
+
+			if idle {
+				cpu::halt();
+			}
+		}
+	}
+
+	fn serial_input(&mut self, byte: u8) -> Option<Input> {
+		let state = self.serial_state;
+		self.serial_state = SerialState::Normal;
+
+		match (state, byte) {
+			(SerialState::Escape, b'[') => {
+				self.serial_state = SerialState::ControlSequence;
+				None
+			}
+			(SerialState::ControlSequence, b'A') => Some(Input::HistoryPrevious),
+			(SerialState::ControlSequence, b'B') => Some(Input::HistoryNext),
+			(SerialState::Escape, _) | (SerialState::ControlSequence, _) => None,
+			(SerialState::CarriageReturn, b'\n') => None,
+			(_, 0x1b) => {
+				self.serial_state = SerialState::Escape;
+				None
+			}
+			(_, b'\r') => {
+				self.serial_state = SerialState::CarriageReturn;
+				Some(Input::Enter)
+			}
+			(_, b'\n') => Some(Input::Enter),
+			(_, 0x08) | (_, 0x7f) => Some(Input::Backspace),
+			(_, b'\t') => Some(Input::Tab),
+			(_, 0x20...0x7e) => Some(Input::Char(byte as char)),
+			_ => None,
+		}
+	}
+
+	fn handle(&mut self, input: Input) {
+		match input {
+			Input::Char(character) => {
+				self.line.push(character);
+				shell_print!("{}", character);
+			}
+			Input::Backspace => {
+				if self.line.pop().is_some() {
+					erase_characters(1);
+				}
+			}
+			Input::Tab => self.complete(),
+			Input::HistoryPrevious => self.history_previous(),
+			Input::HistoryNext => self.history_next(),
+			Input::Enter => {
+				shell_println!("");
+				let line = ::core::mem::replace(&mut self.line, String::new());
+				self.execute(&line);
+				shell_print!("{}", PROMPT);
+			}
+		}
+	}
+
+	fn execute(&mut self, line: &str) {
+		let line = line.trim();
+		if line.is_empty() {
+			return;
+		}
+
+		// Niente duplicati consecutivi nella history.
+		if self.history.last().map(|last| last.as_str()) != Some(line) {
+			if self.history.len() == HISTORY_SIZE {
+				self.history.remove(0);
+			}
+			self.history.push(String::from(line));
+		}
+		self.history_index = None;
+
+		let arguments: Vec<&str> = line.split_whitespace().collect();
+		match commands::find(arguments[0]) {
+			Some(command) => (command.run)(self, &arguments[1..]),
+			None => shell_println!("Unknown command '{}'. Try 'help'.", arguments[0]),
+		}
+	}
+
+	pub fn multiboot_info_pointer(&self) -> usize {
+		self.multiboot_info_pointer
+	}
+
+	// ---
+
+	// LINE EDITING.
+
+	// Completa il nome del comando. Se ci sono più candidati li elenca
+	// e completa il prefisso comune.
+	fn complete(&mut self) {
+		if self.line.contains(' ') {
+			return;
+		}
+
+		let candidates: Vec<&'static str> = commands::all().iter()
+			.map(|command| command.name)
+			.filter(|name| name.starts_with(self.line.as_str()))
+			.collect();
+		if candidates.is_empty() {
+			return;
+		}
+
+		let mut completion = candidates[0];
+		for candidate in &candidates[1..] {
+			let common = completion.bytes().zip(candidate.bytes())
+				.take_while(|&(a, b)| a == b)
+				.count();
+			completion = &completion[..common];
+		}
+
+		if candidates.len() > 1 && completion.len() == self.line.len() {
+			shell_println!("");
+			for candidate in &candidates {
+				shell_print!("{}  ", candidate);
+			}
+			shell_println!("");
+			shell_print!("{}{}", PROMPT, self.line);
+			return;
+		}
+
+		let suffix = &completion[self.line.len()..];
+		self.line.push_str(suffix);
+		shell_print!("{}", suffix);
+		if candidates.len() == 1 {
+			self.line.push(' ');
+			shell_print!(" ");
+		}
+	}
+
+	fn history_previous(&mut self) {
+		let index = match self.history_index {
+			Some(0) => return,
+			Some(index) => index - 1,
+			None if self.history.is_empty() => return,
+			None => self.history.len() - 1,
+		};
+		self.history_index = Some(index);
+
+		let line = self.history[index].clone();
+		self.replace_line(line);
+	}
+
+	fn history_next(&mut self) {
+		let index = match self.history_index {
+			Some(index) if index + 1 < self.history.len() => index + 1,
+			Some(_) => {
+				self.history_index = None;
+				self.replace_line(String::new());
+				return;
+			}
+			None => return,
+		};
+		self.history_index = Some(index);
+
+		let line = self.history[index].clone();
+		self.replace_line(line);
+	}
+
+	fn replace_line(&mut self, line: String) {
+		erase_characters(self.line.chars().count());
+		shell_print!("{}", line);
+		self.line = line;
+	}
+}
+
+// ---
+
+// Destinazione fmt::Write per l'output della shell, su VGA e seriale.
+pub struct ShellOutput;
+
+impl fmt::Write for ShellOutput {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		shell_print!("{}", s);
+		Ok(())
+	}
+}
+
+// Cancella gli ultimi `count` caratteri su VGA e seriale.
+fn erase_characters(count: usize) {
+	for _ in 0..count {
+		shell_print!("\x08 \x08");
+	}
+}
+
+// Avvia la shell sulla console. Va chiamata per ultima in rust_main.
+pub fn run(multiboot_info_pointer: usize) -> ! {
+	Shell::new(multiboot_info_pointer).run()
+}
+
+// ---
@@ -0,0 +1,118 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+use x86::io::{inb, outb};
+use cpu;
+use interrupts::{self, irq};
+
+// PIT 8253/8254.
+// Il canale 0 è collegato alla IRQ 0 e divide un clock fisso di
+// 1.193182 MHz per un divisore a 16 bit.
+
+pub const PIT_FREQUENCY: usize = 1_193_182;
+pub const DEFAULT_FREQUENCY: usize = 1000;
+
+const CHANNEL_0_DATA: u16 = 0x40;
+const CHANNEL_2_DATA: u16 = 0x42;
+const COMMAND: u16 = 0x43;
+
+// Canale 0, byte basso poi alto, modo 2 (rate generator), binario.
+const CHANNEL_0_RATE_GENERATOR: u8 = 0b00_11_010_0;
+// Canale 2, byte basso poi alto, modo 0 (interrupt on terminal count).
+const CHANNEL_2_ONE_SHOT: u8 = 0b10_11_000_0;
+
+// Porta B del controller di sistema: gate del canale 2 (bit 0),
+// speaker (bit 1) e uscita del canale 2 (bit 5).
+const SYSTEM_CONTROL_PORT_B: u16 = 0x61;
+const CHANNEL_2_GATE: u8 = 1 << 0;
+const SPEAKER_ENABLE: u8 = 1 << 1;
+const CHANNEL_2_OUTPUT: u8 = 1 << 5;
+
+const TIMER_IRQ: u8 = 0;
+
+static TICKS: AtomicUsize = AtomicUsize::new(0);
+static FREQUENCY: AtomicUsize = AtomicUsize::new(0);
+
+// Programma il canale 0 a `frequency` Hz e registra l'handler della IRQ 0.
+// La frequenza reale è la più vicina ottenibile con il divisore.
+pub fn init(frequency: usize) {
+	assert! (frequency > 0, "Invalid timer frequency {}.", frequency);
+
+	// Un divisore 0 vale 65536, la frequenza minima (~18.2 Hz).
+	let divisor = match (PIT_FREQUENCY + frequency / 2) / frequency {
+		0 => 1,
+		divisor if divisor >= 0x10000 => 0x10000,
+		divisor => divisor,
+	};
+	FREQUENCY.store(PIT_FREQUENCY / divisor, Ordering::SeqCst);
+
+	interrupts::without_interrupts(|| unsafe {
+		outb(COMMAND, CHANNEL_0_RATE_GENERATOR);
+		outb(CHANNEL_0_DATA, divisor as u8);
+		outb(CHANNEL_0_DATA, (divisor >> 8) as u8);
+	});
+
+	irq::register(TIMER_IRQ, tick);
+}
+
+fn tick(_irq: u8) {
+	TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+// Attende `ms` millisecondi (al massimo 54) contando sul canale 2, senza
+// interrupt: serve a calibrare altri clock prima che il timer sia attivo.
+pub fn busy_wait_ms(ms: usize) {
+	let count = PIT_FREQUENCY * ms / 1000;
+	assert! (count > 0 && count <= 0xffff, "Invalid PIT busy wait of {} ms.", ms);
+
+	unsafe {
+		// Gate alto e speaker spento: il conteggio parte al caricamento.
+		let port_b = inb(SYSTEM_CONTROL_PORT_B);
+		outb(SYSTEM_CONTROL_PORT_B, (port_b & !SPEAKER_ENABLE) | CHANNEL_2_GATE);
+
+		outb(COMMAND, CHANNEL_2_ONE_SHOT);
+		outb(CHANNEL_2_DATA, count as u8);
+		outb(CHANNEL_2_DATA, (count >> 8) as u8);
+
+		while inb(SYSTEM_CONTROL_PORT_B) & CHANNEL_2_OUTPUT == 0 {}
+
+		outb(SYSTEM_CONTROL_PORT_B, port_b);
+	}
+}
+
+// ---
+
+// TIME.
+
+// Tick dall'avvio del timer. Monotono: non torna mai indietro.
+pub fn ticks() -> usize {
+	TICKS.load(Ordering::Relaxed)
+}
+
+// Frequenza effettiva dei tick in Hz, 0 se il timer non è attivo.
+pub fn frequency() -> usize {
+	FREQUENCY.load(Ordering::Relaxed)
+}
+
+// Millisecondi dall'avvio del timer.
+pub fn uptime() -> usize {
+	match frequency() {
+		0 => 0,
+		frequency => ticks() * 1000 / frequency,
+	}
+}
+
+// Attende almeno `ms` millisecondi fermando la CPU fra un tick e l'altro.
+// Richiede il timer attivo e gli interrupt abilitati.
+pub fn sleep_ms(ms: usize) {
+	let frequency = frequency();
+	assert! (frequency != 0, "sleep_ms called before timer::init.");
+	assert! (interrupts::are_enabled(), "sleep_ms called with interrupts disabled.");
+
+	// Il tick corrente è già in parte trascorso: se ne aspetta uno in più.
+	let ticks_to_wait = (ms * frequency + 999) / 1000 + 1;
+	let target = ticks() + ticks_to_wait;
+	while ticks() < target {
+		cpu::halt();
+	}
+}
+
+// ---
@@ -1,35 +1,40 @@
-use core::ptr::Unique;
+use core::cmp::min;
+use core::ptr::NonNull;
 use core::fmt::Write;
+use alloc::vec::Vec;
 use volatile::Volatile;
 use spin::Mutex;
+use x86::io::{inb, outb};
 
 const BUFFER_HEIGHT: usize = 25;
 const BUFFER_WIDTH: usize = 80;
+// Righe uscite dallo schermo che si possono ancora rivedere, una volta
+// allocata la scrollback (enable_scrollback).
+const SCROLLBACK_LINES: usize = 500;
+const TAB_WIDTH: usize = 8;
 
 
 // TEMPORANEO.
 pub fn print_something() {
-    let mut writer = Writer {
-        column_position: 15,
-        row_position: 12,
-        color_code: ColorCode::new(Color::Yellow, Color::Blue),
-        buffer: unsafe { Unique::new(0xb8000 as *mut _) },
-    };
-
-    writer.write_byte(b'H');
-    writer.write_byte(b'y');
-    
-    write!(writer, "\nThe numbers are {} and {}", 42, 1.0/3.0);
+	with_overlay(12, 15, ColorCode::new(Color::Yellow, Color::Blue), |writer| {
+		writer.write_byte(b'H');
+		writer.write_byte(b'y');
+		
+		write!(writer, "\nThe numbers are {} and {}", 42, 1.0/3.0);
+	});
 }
 // FINE TEMPORANEO.
 
 
 // MACROS.
+// Gli handler delle IRQ stampano: con WRITER preso non devono arrivare.
 macro_rules! print {
     ($($arg:tt)*) => ({
-            use core::fmt::Write;
-            let mut writer = $crate::vga_buffer::WRITER.lock();
-            writer.write_fmt(format_args!($($arg)*)).unwrap();
+            $crate::interrupts::without_interrupts(|| {
+                use core::fmt::Write;
+                let mut writer = $crate::vga_buffer::WRITER.lock();
+                writer.write_fmt(format_args!($($arg)*)).unwrap();
+            });
     });
 }
 
@@ -37,28 +42,77 @@ macro_rules! println {
     ($fmt:expr) => (print!(concat!($fmt, "\n")));
     ($fmt:expr, $($arg:tt)*) => (print!(concat!($fmt, "\n"), $($arg)*));
 }
+
+// Come println!, con davanti il tempo dall'avvio in grigio.
+// Va anche sulla seriale, con gli stessi colori.
+macro_rules! log {
+    ($fmt:expr) => ({
+            let timestamp = $crate::clock::Timestamp::now();
+            println!(concat!("\x1b[90m{}\x1b[0m ", $fmt), timestamp);
+            serial_println!(concat!("\x1b[90m{}\x1b[0m ", $fmt), timestamp);
+    });
+    ($fmt:expr, $($arg:tt)*) => ({
+            let timestamp = $crate::clock::Timestamp::now();
+            println!(concat!("\x1b[90m{}\x1b[0m ", $fmt), timestamp, $($arg)*);
+            serial_println!(concat!("\x1b[90m{}\x1b[0m ", $fmt), timestamp, $($arg)*);
+    });
+}
 // ---
 
 
+// Lo schermo cancellato resta nella scrollback.
 pub fn clear_screen() {
-    for _ in 0..BUFFER_HEIGHT {
-        println!("");
-    }
+	::interrupts::without_interrupts(|| WRITER.lock().clear());
+}
+
+// Alloca la scrollback nell'heap: da chiamare dopo memory::init. Prima le
+// righe che escono dallo schermo vanno perse. Le allocazioni avvengono qui,
+// fuori da WRITER: stampando non si alloca mai.
+pub fn enable_scrollback() {
+	let scrollback = Vec::with_capacity(SCROLLBACK_LINES);
+	let mut saved_screen = Vec::with_capacity(BUFFER_HEIGHT);
+	for _ in 0..BUFFER_HEIGHT {
+		saved_screen.push([BLANK; BUFFER_WIDTH]);
+	}
+	
+	::interrupts::without_interrupts(|| {
+		let mut writer = WRITER.lock();
+		writer.scrollback = Some(scrollback);
+		writer.saved_screen = Some(saved_screen);
+	});
 }
 
 pub fn print_centered(s: &str) {
 	let len = s.len() + 4;
-    let mut writer = Writer {
-        column_position: 40 - (len / 2),
-        row_position: 11,
-        color_code: ColorCode::new(Color::Yellow, Color::Blue),
-        buffer: unsafe { Unique::new(0xb8000 as *mut _) },
-    };
+	let color_code = ColorCode::new(Color::Yellow, Color::Blue);
+	
+	with_overlay(11, 40 - (len / 2), color_code, |writer| {
+		let sep = "*";
+		write_separator(writer, sep, len);
+		write_string_in_separator(writer, s, len, sep);
+		write_separator(writer, sep, len);
+	});
+}
 
-	let sep = "*";
-	write_separator(&mut writer, sep, len);
-	write_string_in_separator(&mut writer, s, len, sep);
-    write_separator(&mut writer, sep, len);
+// Scrive con WRITER spostato in (row, column) e con altri colori, poi
+// rimette posizione e colori com'erano.
+fn with_overlay<F>(row: usize, column: usize, color_code: ColorCode, f: F)
+	where F: FnOnce(&mut Writer)
+{
+	::interrupts::without_interrupts(|| {
+		let mut writer = WRITER.lock();
+		let saved = (writer.row_position, writer.column_position, writer.color_code);
+		
+		writer.row_position = row;
+		writer.column_position = column;
+		writer.color_code = color_code;
+		f(&mut writer);
+		
+		writer.row_position = saved.0;
+		writer.column_position = saved.1;
+		writer.color_code = saved.2;
+		writer.update_cursor();
+	});
 }
 
 pub fn write_string_in_separator(writer: &mut Writer, s: &str, s_len: usize, sep: &str) {
@@ -76,10 +130,43 @@ pub fn write_separator(writer: &mut Writer, sep: &str, len: usize) {
 pub static WRITER: Mutex<Writer> = Mutex::new(Writer {
 	column_position: 0,
 	row_position: BUFFER_HEIGHT - 1,
-	color_code: ColorCode::new(Color::LightGreen, Color::Black),
-	buffer: unsafe { Unique::new(0xb8000 as *mut _) },
+	color_code: DEFAULT_COLOR,
+	buffer: unsafe { NonNull::new_unchecked(0xb8000 as *mut _) },
+	scrollback: None,
+	scrollback_start: 0,
+	saved_screen: None,
+	scroll_offset: 0,
+	escape: EscapeState::Normal,
+	escape_parameters: [0; MAX_ESCAPE_PARAMETERS],
+	escape_parameter_count: 0,
+	saved_position: (BUFFER_HEIGHT - 1, 0),
+	cursor_shape: (DEFAULT_CURSOR_START, DEFAULT_CURSOR_END),
+	cursor_hidden: false,
 });
 
+// Shift+PgUp/PgDn: una schermata alla volta.
+pub fn scroll_page_up() {
+	::interrupts::without_interrupts(|| WRITER.lock().scroll_up(BUFFER_HEIGHT - 1));
+}
+
+pub fn scroll_page_down() {
+	::interrupts::without_interrupts(|| WRITER.lock().scroll_down(BUFFER_HEIGHT - 1));
+}
+
+// Forma del cursore: righe di scansione da `start` a `end` (0-15) del
+// carattere. (14, 15) è la lineetta, (0, 15) il blocco pieno.
+pub fn set_cursor_shape(start: u8, end: u8) {
+	::interrupts::without_interrupts(|| WRITER.lock().set_cursor_shape(start, end));
+}
+
+pub fn hide_cursor() {
+	::interrupts::without_interrupts(|| WRITER.lock().hide_cursor());
+}
+
+pub fn show_cursor() {
+	::interrupts::without_interrupts(|| WRITER.lock().show_cursor());
+}
+
 
 #[allow(dead_code)]
 #[repr(u8)]
@@ -111,7 +198,17 @@ impl ColorCode {
 	const fn new(foreground: Color, background: Color) -> ColorCode {
 		ColorCode((background as u8) << 4 | (foreground as u8))
 	}
+	
+	fn with_foreground(self, foreground: u8) -> ColorCode {
+		ColorCode((self.0 & 0xf0) | (foreground & 0x0f))
+	}
+	
+	fn with_background(self, background: u8) -> ColorCode {
+		ColorCode((self.0 & 0x0f) | (background & 0x0f) << 4)
+	}
 }
+
+const DEFAULT_COLOR: ColorCode = ColorCode::new(Color::LightGreen, Color::Black);
 // ---
 
 
@@ -122,6 +219,11 @@ struct ScreenChar {
 	color_code: ColorCode,
 }
 
+const BLANK: ScreenChar = ScreenChar {
+	ascii_character: b' ',
+	color_code: DEFAULT_COLOR,
+};
+
 
 struct Buffer {
 	chars: [[Volatile<ScreenChar>; BUFFER_WIDTH]; BUFFER_HEIGHT],
@@ -129,17 +231,53 @@ struct Buffer {
 
 
 // WRITER
+// Si scrive direttamente sulla VGA. Solo quando si inizia a guardare la
+// scrollback lo schermo viene copiato in `saved_screen`, e al ritorno in
+// fondo si ridisegna da lì. Ogni scrittura riporta prima la vista in fondo.
 pub struct Writer {
 	column_position: usize,
 	row_position: usize,
 	color_code: ColorCode,
-	buffer: Unique<Buffer>,
+	buffer: NonNull<Buffer>,
+	// Ring delle righe uscite dall'alto dello schermo, nell'heap.
+	// Riempito fino a SCROLLBACK_LINES, poi si sovrascrive da
+	// scrollback_start.
+	scrollback: Option<Vec<[ScreenChar; BUFFER_WIDTH]>>,
+	scrollback_start: usize,
+	saved_screen: Option<Vec<[ScreenChar; BUFFER_WIDTH]>>,
+	// Righe di scrollback visibili sopra lo schermo, 0 = in fondo.
+	scroll_offset: usize,
+	// Sequenza ANSI in corso.
+	escape: EscapeState,
+	escape_parameters: [usize; MAX_ESCAPE_PARAMETERS],
+	escape_parameter_count: usize,
+	// Riga e colonna salvate con ESC 7 o CSI s.
+	saved_position: (usize, usize),
+	cursor_shape: (u8, u8),
+	cursor_hidden: bool,
 }
 
+// Il buffer VGA è uno solo e si raggiunge solo attraverso WRITER.
+unsafe impl Send for Writer {}
+
 impl Writer {
 	pub fn write_byte(&mut self, byte: u8) {
+		// Il nuovo output riporta la vista in fondo.
+		self.snap_to_bottom();
+		
+		match self.escape {
+			EscapeState::Escape => return self.escape_byte(byte),
+			EscapeState::ControlSequence => return self.control_sequence_byte(byte),
+			EscapeState::Normal => {}
+		}
+		
 		match byte {
+			ESCAPE => self.escape = EscapeState::Escape,
 			b'\n' => self.new_line(),
+			b'\r' => self.column_position = 0,
+			b'\t' => self.tab(),
+			BACKSPACE => self.backspace(),
+			FORM_FEED => self.clear(),
 			byte => {
 				if self.column_position >= BUFFER_WIDTH {
 					self.new_line();
@@ -149,7 +287,7 @@ impl Writer {
 				let col = self.column_position;
 				
 				let color_code = self.color_code;
-				self.buffer().chars[row][col].write(ScreenChar {
+				self.put(row, col, ScreenChar {
 					ascii_character: byte,
 					color_code: color_code,
 				});
@@ -160,13 +298,26 @@ impl Writer {
 	}
 
 	pub fn write_string(&mut self, s: &str) {
-		for byte in s.bytes() {
-			self.write_byte(byte);
+		for character in s.chars() {
+			self.write_byte(to_code_page_437(character));
 		}
+		self.update_cursor();
 	}
 	
 	fn buffer(&mut self) -> &mut  Buffer {
-		unsafe { self.buffer.get_mut() }
+		unsafe { self.buffer.as_mut() }
+	}
+	
+	fn put(&mut self, row: usize, col: usize, character: ScreenChar) {
+		self.buffer().chars[row][col].write(character);
+	}
+	
+	fn read_row(&mut self, row: usize) -> [ScreenChar; BUFFER_WIDTH] {
+		let mut line = [BLANK; BUFFER_WIDTH];
+		for col in 0..BUFFER_WIDTH {
+			line[col] = self.buffer().chars[row][col].read();
+		}
+		line
 	}
 	
 	/*
@@ -181,16 +332,160 @@ impl Writer {
 	}
 	*/
 	fn new_line(&mut self) {
+		self.column_position = 0;
+		if self.row_position < BUFFER_HEIGHT - 1 {
+			self.row_position += 1;
+			return;
+		}
+		
+		// La riga 0 non si butta: finisce nella scrollback.
+		let top = self.read_row(0);
+		self.push_scrollback(top);
+		
 		for row in 1..BUFFER_HEIGHT {
 			for col in 0..BUFFER_WIDTH {
-				let buffer = self.buffer();
-				let character = buffer.chars[row][col].read();
-				buffer.chars[row - 1][col].write(character);
+				let character = self.buffer().chars[row][col].read();
+				self.buffer().chars[row - 1][col].write(character);
 			}
 		}
 		self.clear_row(BUFFER_HEIGHT-1);
 		self.column_position = 0;
-}
+	}
+	
+	// La capacità è già allocata: push non alloca mai.
+	fn push_scrollback(&mut self, line: [ScreenChar; BUFFER_WIDTH]) {
+		let start = self.scrollback_start;
+		if let Some(ref mut lines) = self.scrollback {
+			if lines.len() < SCROLLBACK_LINES {
+				lines.push(line);
+			}
+			else {
+				lines[start] = line;
+				self.scrollback_start = (start + 1) % SCROLLBACK_LINES;
+			}
+		}
+	}
+	
+	fn scrollback_len(&self) -> usize {
+		self.scrollback.as_ref().map_or(0, |lines| lines.len())
+	}
+	
+	// ---
+	
+	// SCROLLBACK.
+	
+	pub fn scroll_up(&mut self, lines: usize) {
+		let offset = min(self.scroll_offset + lines, self.scrollback_len());
+		if offset == self.scroll_offset {
+			return;
+		}
+		
+		if self.scroll_offset == 0 {
+			self.save_screen();
+		}
+		self.scroll_offset = offset;
+		self.redraw();
+	}
+	
+	pub fn scroll_down(&mut self, lines: usize) {
+		let offset = self.scroll_offset;
+		if offset == 0 {
+			return;
+		}
+		
+		self.scroll_offset = if lines > offset { 0 } else { offset - lines };
+		self.redraw();
+	}
+	
+	fn save_screen(&mut self) {
+		for row in 0..BUFFER_HEIGHT {
+			let line = self.read_row(row);
+			if let Some(ref mut saved_screen) = self.saved_screen {
+				saved_screen[row] = line;
+			}
+		}
+	}
+	
+	fn snap_to_bottom(&mut self) {
+		if self.scroll_offset != 0 {
+			self.scroll_offset = 0;
+			self.redraw();
+		}
+	}
+	
+	// Ridisegna la VGA: le ultime `scroll_offset` righe di scrollback
+	// seguite dalla parte alta dello schermo salvato. Si arriva qui solo
+	// con la scrollback allocata.
+	fn redraw(&mut self) {
+		let scrollback_len = self.scrollback_len();
+		let first_line = scrollback_len - self.scroll_offset;
+		for row in 0..BUFFER_HEIGHT {
+			let line = first_line + row;
+			let characters = if line < scrollback_len {
+				let index = (self.scrollback_start + line) % SCROLLBACK_LINES;
+				self.scrollback.as_ref().unwrap()[index]
+			}
+			else {
+				self.saved_screen.as_ref().unwrap()[line - scrollback_len]
+			};
+			
+			for col in 0..BUFFER_WIDTH {
+				self.buffer().chars[row][col].write(characters[col]);
+			}
+		}
+		self.update_cursor();
+	}
+	
+	// ---
+	
+	// Cancella il carattere prima del cursore. A inizio riga torna in
+	// fondo alla riga precedente, dove continua una riga lunga andata a capo.
+	pub fn backspace(&mut self) {
+		self.snap_to_bottom();
+		if self.column_position == 0 {
+			if self.row_position == 0 {
+				return;
+			}
+			self.row_position -= 1;
+			self.column_position = BUFFER_WIDTH;
+		}
+		
+		self.column_position -= 1;
+		let row = self.row_position;
+		let col = self.column_position;
+		let color_code = self.color_code;
+		self.put(row, col, ScreenChar {
+			ascii_character: b' ',
+			color_code: color_code,
+		});
+		self.update_cursor();
+	}
+	
+	// Avanza alla prossima tabulazione senza cancellare. Oltre l'ultima
+	// il cursore resta a fine riga.
+	fn tab(&mut self) {
+		if self.column_position >= BUFFER_WIDTH {
+			self.new_line();
+		}
+		
+		let next_stop = (self.column_position / TAB_WIDTH + 1) * TAB_WIDTH;
+		self.column_position = min(next_stop, BUFFER_WIDTH);
+	}
+	
+	// Pulisce lo schermo e riporta il cursore in alto a sinistra.
+	// Le righe cancellate finiscono nella scrollback.
+	pub fn clear(&mut self) {
+		self.snap_to_bottom();
+		for row in 0..BUFFER_HEIGHT {
+			let line = self.read_row(row);
+			self.push_scrollback(line);
+			self.clear_row(row);
+		}
+		
+		self.row_position = 0;
+		self.column_position = 0;
+		self.update_cursor();
+	}
 
 	fn down_one_row(&mut self) {
 		self.row_position += 1;
@@ -237,18 +532,293 @@ impl Writer {
 			color_code: self.color_code,
 		};
 		for col in 0..BUFFER_WIDTH {
-			self.buffer().chars[row][col].write(blank);
+			self.put(row, col, blank);
 		}
 	}
 }
 
-impl ::core::fmt::Write for Writer {
-	fn write_str(&mut self, s: &str) -> ::core::fmt::Result {
-		for byte in s.bytes() {
-			self.write_byte(byte);
+// ANSI
+// Un sottoinsieme di VT100: ESC [ parametri ; ... comando.
+//   CSI n m       colori SGR (0, 1, 30-37, 39, 40-47, 49, 90-97, 100-107)
+//   CSI r ; c H   cursore alla riga r, colonna c (da 1)
+//   CSI n A/B/C/D cursore su, giù, avanti, indietro
+//   CSI n J/K     cancella schermo/riga (0 dal cursore, 1 fino al cursore, 2 tutto)
+//   CSI s/u, ESC 7/8  salva e ripristina il cursore
+// Le sequenze sconosciute vengono ignorate.
+
+const ESCAPE: u8 = 0x1b;
+const BACKSPACE: u8 = 0x08;
+const FORM_FEED: u8 = 0x0c;
+const MAX_ESCAPE_PARAMETERS: usize = 4;
+
+// Colori ANSI (nero, rosso, verde, giallo, blu, magenta, ciano, bianco)
+// nell'ordine della VGA. +8 per le versioni chiare.
+const ANSI_TO_VGA: [u8; 8] = [
+	Color::Black as u8, Color::Red as u8, Color::Green as u8, Color::Brown as u8,
+	Color::Blue as u8, Color::Magenta as u8, Color::Cyan as u8, Color::LightGray as u8,
+];
+const BRIGHT: u8 = 8;
+
+#[derive(Clone, Copy, PartialEq)]
+enum EscapeState {
+	Normal,
+	Escape,
+	ControlSequence,
+}
+
+impl Writer {
+	fn escape_byte(&mut self, byte: u8) {
+		self.escape = EscapeState::Normal;
+		match byte {
+			b'[' => {
+				self.escape = EscapeState::ControlSequence;
+				self.escape_parameters = [0; MAX_ESCAPE_PARAMETERS];
+				self.escape_parameter_count = 0;
+			}
+			b'7' => self.save_position(),
+			b'8' => self.restore_position(),
+			_ => {}
+		}
+	}
+	
+	fn control_sequence_byte(&mut self, byte: u8) {
+		match byte {
+			b'0'...b'9' => {
+				if self.escape_parameter_count == 0 {
+					self.escape_parameter_count = 1;
+				}
+				let parameter = &mut self.escape_parameters[self.escape_parameter_count - 1];
+				*parameter = parameter.saturating_mul(10) + (byte - b'0') as usize;
+			}
+			b';' => {
+				if self.escape_parameter_count == 0 {
+					self.escape_parameter_count = 1;
+				}
+				// I parametri in più si perdono.
+				if self.escape_parameter_count < MAX_ESCAPE_PARAMETERS {
+					self.escape_parameter_count += 1;
+				}
+			}
+			// Byte finale: esegue il comando.
+			0x40...0x7e => {
+				self.escape = EscapeState::Normal;
+				self.execute_control_sequence(byte);
+			}
+			// Byte intermedi, come '?': ignorati.
+			_ => {}
+		}
+	}
+	
+	// Parametro `index`, o `default` se manca o è 0.
+	fn parameter(&self, index: usize, default: usize) -> usize {
+		match self.escape_parameters[index] {
+			0 => default,
+			value => value,
+		}
+	}
+	
+	fn execute_control_sequence(&mut self, command: u8) {
+		let row = self.row_position;
+		let col = if self.column_position < BUFFER_WIDTH {
+			self.column_position
+		}
+		else {
+			BUFFER_WIDTH - 1
+		};
+		
+		match command {
+			b'H' | b'f' => {
+				self.row_position = min(self.parameter(0, 1), BUFFER_HEIGHT) - 1;
+				self.column_position = min(self.parameter(1, 1), BUFFER_WIDTH) - 1;
+			}
+			b'A' => self.row_position = row.saturating_sub(self.parameter(0, 1)),
+			b'B' => self.row_position = min(row + self.parameter(0, 1), BUFFER_HEIGHT - 1),
+			b'C' => self.column_position = min(col + self.parameter(0, 1), BUFFER_WIDTH - 1),
+			b'D' => self.column_position = col.saturating_sub(self.parameter(0, 1)),
+			b'J' => match self.escape_parameters[0] {
+				0 => {
+					self.erase(row, col, BUFFER_WIDTH);
+					for row in (row + 1)..BUFFER_HEIGHT {
+						self.clear_row(row);
+					}
+				}
+				1 => {
+					for row in 0..row {
+						self.clear_row(row);
+					}
+					self.erase(row, 0, col + 1);
+				}
+				_ => {
+					for row in 0..BUFFER_HEIGHT {
+						self.clear_row(row);
+					}
+				}
+			},
+			b'K' => match self.escape_parameters[0] {
+				0 => self.erase(row, col, BUFFER_WIDTH),
+				1 => self.erase(row, 0, col + 1),
+				_ => self.clear_row(row),
+			},
+			b'm' => self.select_graphic_rendition(),
+			b's' => self.save_position(),
+			b'u' => self.restore_position(),
+			_ => {}
+		}
+	}
+	
+	fn select_graphic_rendition(&mut self) {
+		// CSI m senza parametri equivale a CSI 0 m.
+		let count = if self.escape_parameter_count == 0 { 1 } else { self.escape_parameter_count };
+		for index in 0..count {
+			let color_code = self.color_code;
+			self.color_code = match self.escape_parameters[index] {
+				0 => DEFAULT_COLOR,
+				// Grassetto: colore chiaro.
+				1 => ColorCode(color_code.0 | BRIGHT),
+				code @ 30...37 => color_code.with_foreground(ANSI_TO_VGA[code - 30]),
+				39 => color_code.with_foreground(DEFAULT_COLOR.0),
+				code @ 40...47 => color_code.with_background(ANSI_TO_VGA[code - 40]),
+				49 => color_code.with_background(DEFAULT_COLOR.0 >> 4),
+				code @ 90...97 => color_code.with_foreground(ANSI_TO_VGA[code - 90] | BRIGHT),
+				code @ 100...107 => color_code.with_background(ANSI_TO_VGA[code - 100] | BRIGHT),
+				_ => color_code,
+			};
+		}
+	}
+	
+	// Cancella le colonne start..end della riga.
+	fn erase(&mut self, row: usize, start: usize, end: usize) {
+		let blank = ScreenChar {
+			ascii_character: b' ',
+			color_code: self.color_code,
+		};
+		for col in start..end {
+			self.put(row, col, blank);
+		}
+	}
+	
+	fn save_position(&mut self) {
+		self.saved_position = (self.row_position, self.column_position);
+	}
+	
+	fn restore_position(&mut self) {
+		let (row, col) = self.saved_position;
+		self.row_position = row;
+		self.column_position = col;
+	}
+}
+// ---
+
+
+// CURSOR
+// Il cursore lampeggiante è gestito dal CRT controller: si scrive
+// l'indice del registro su 0x3D4 e il valore su 0x3D5.
+
+const CRTC_ADDRESS: u16 = 0x3d4;
+const CRTC_DATA: u16 = 0x3d5;
+
+const CRTC_CURSOR_START: u8 = 0x0a;
+const CRTC_CURSOR_END: u8 = 0x0b;
+const CRTC_CURSOR_LOCATION_HIGH: u8 = 0x0e;
+const CRTC_CURSOR_LOCATION_LOW: u8 = 0x0f;
+
+const CURSOR_DISABLE: u8 = 1 << 5;
+const DEFAULT_CURSOR_START: u8 = 14;
+const DEFAULT_CURSOR_END: u8 = 15;
+
+impl Writer {
+	// Porta il cursore hardware sulla posizione del writer. Mentre si
+	// guarda la scrollback lo sposta fuori dallo schermo.
+	fn update_cursor(&mut self) {
+		let position = if self.scroll_offset != 0 {
+			BUFFER_WIDTH * BUFFER_HEIGHT
+		}
+		else {
+			self.row_position * BUFFER_WIDTH + min(self.column_position, BUFFER_WIDTH - 1)
+		};
+		
+		write_crtc(CRTC_CURSOR_LOCATION_HIGH, (position >> 8) as u8);
+		write_crtc(CRTC_CURSOR_LOCATION_LOW, position as u8);
+	}
+	
+	pub fn set_cursor_shape(&mut self, start: u8, end: u8) {
+		self.cursor_shape = (start & 0x1f, end & 0x1f);
+		if !self.cursor_hidden {
+			self.program_cursor_shape(false);
 		}
+	}
+	
+	pub fn hide_cursor(&mut self) {
+		self.cursor_hidden = true;
+		self.program_cursor_shape(true);
+	}
+	
+	pub fn show_cursor(&mut self) {
+		self.cursor_hidden = false;
+		self.program_cursor_shape(false);
+		self.update_cursor();
+	}
+	
+	// I bit alti dei due registri sono riservati e vanno preservati.
+	fn program_cursor_shape(&mut self, disabled: bool) {
+		let (start, end) = self.cursor_shape;
+		let start = if disabled { start | CURSOR_DISABLE } else { start };
 		
+		write_crtc(CRTC_CURSOR_START, (read_crtc(CRTC_CURSOR_START) & 0xc0) | start);
+		write_crtc(CRTC_CURSOR_END, (read_crtc(CRTC_CURSOR_END) & 0xe0) | end);
+	}
+}
+
+fn read_crtc(register: u8) -> u8 {
+	unsafe {
+		outb(CRTC_ADDRESS, register);
+		inb(CRTC_DATA)
+	}
+}
+
+fn write_crtc(register: u8, value: u8) {
+	unsafe {
+		outb(CRTC_ADDRESS, register);
+		outb(CRTC_DATA, value);
+	}
+}
+// ---
+
+
+impl ::core::fmt::Write for Writer {
+	fn write_str(&mut self, s: &str) -> ::core::fmt::Result {
+		self.write_string(s);
 		Ok(())
 	}
 }
+
+// Destinazione per le funzioni che scrivono su un fmt::Write qualsiasi.
+// Passa da print!, quindi WRITER non resta preso fra una scrittura e l'altra.
+pub struct Screen;
+
+impl ::core::fmt::Write for Screen {
+	fn write_str(&mut self, s: &str) -> ::core::fmt::Result {
+		print!("{}", s);
+		Ok(())
+	}
+}
+// ---
+
+
+// CODE PAGE 437
+// Il font della VGA: ASCII più lettere accentate e simboli nei byte alti.
+// I caratteri che non ci sono diventano un quadratino.
+fn to_code_page_437(character: char) -> u8 {
+	match character {
+		'\0'...'\x7f' => character as u8,
+		'Ç' => 0x80, 'ü' => 0x81, 'é' => 0x82, 'â' => 0x83, 'ä' => 0x84,
+		'à' => 0x85, 'å' => 0x86, 'ç' => 0x87, 'ê' => 0x88, 'ë' => 0x89,
+		'è' => 0x8a, 'ï' => 0x8b, 'î' => 0x8c, 'ì' => 0x8d, 'Ä' => 0x8e,
+		'Å' => 0x8f, 'É' => 0x90, 'ô' => 0x93, 'ö' => 0x94, 'ò' => 0x95,
+		'û' => 0x96, 'ù' => 0x97, 'Ö' => 0x99, 'Ü' => 0x9a, '£' => 0x9c,
+		'á' => 0xa0, 'í' => 0xa1, 'ó' => 0xa2, 'ú' => 0xa3, 'ñ' => 0xa4,
+		'Ñ' => 0xa5, '§' => 0x15, '°' => 0xf8,
+		_ => 0xfe,
+	}
+}
 // ---